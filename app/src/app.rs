@@ -1,126 +1,218 @@
 #[cfg(not(feature = "std"))]
-use alloc::{
-    string::{String, ToString},
-    vec::Vec,
-};
+use alloc::{boxed::Box, vec::Vec};
 
-use embassy_time::Instant;
-use log::Level;
+use embassy_time::{Instant, with_deadline};
 use ratatui::{
     Frame, Terminal,
     buffer::Buffer,
-    layout::{Constraint, Layout, Margin, Rect},
+    layout::Rect,
     prelude::{Backend, Widget},
     style::{Color, Style, Stylize},
     text::Line,
-    widgets::{Block, Padding, Paragraph, Tabs, Wrap},
+    widgets::{Block, Padding, Paragraph, Tabs},
 };
 #[cfg(feature = "std")]
-use std::{string::String, vec::Vec};
-
-use strum::IntoEnumIterator;
+use std::{boxed::Box, vec::Vec};
 
 use crate::{
-    Stats, StickHat,
-    events::{self, EVENTS, Event, Receiver, Sender},
+    StickHat, auth,
+    battery::{Limits, PowerSource},
+    clock,
+    events::{self, Button, Event, Receiver, Sender, Stats},
+    idle::{self, IdleState, IdleTimer},
+    input::{Gesture, Recognizer},
     layout::AppLayout,
-    logger,
+    overlay::{Dismissed, Overlays},
+    role::Role,
+    screens::{self, Context, Screen},
+    sleep::Snapshot,
 };
 
-#[cfg(feature = "tv")]
-use crate::remote::{TVRemote, TVState};
+/// Holding C this long powers off, the header is fully red by then.
+const POWER_OFF_HOLD_MS: u64 = 3000;
+/// The footer counts down from here on, a long C let go before it is the next tab.
+const POWER_OFF_HINT_MS: u64 = 1000;
+/// Header while the battery is low.
+const LOW_BATTERY_BG: Color = Color::Rgb(180, 30, 30);
+/// Critical readings in a row before powering off, one bad reading isn't enough.
+const CRITICAL_READINGS: u8 = 3;
+
+/// Why [`App::run`] returned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    PowerOff,
+    /// Keep [`App::snapshot`] for the next app.
+    DeepSleep,
+}
 
 pub struct App {
-    #[allow(dead_code)]
     sender: Sender,
     receiver: Receiver,
-    exit: bool,
+    exit: Option<Exit>,
+    role: Role,
     layout: AppLayout,
-    c_start: Option<Instant>,
-    b_start: Option<Instant>,
-    selected_tab: SelectedTab,
+    input: Recognizer,
+    idle: IdleTimer,
+    overlays: Overlays,
+    /// Every tab, in order, including the ones not enabled in this role.
+    screens: Vec<Box<dyn Screen>>,
+    selected: usize,
     tab_touched: bool,
-    stats: events::Stats,
-    #[cfg(feature = "tv")]
-    tv: TVState,
     hat: Option<StickHat>,
+    /// Deep sleep instead of staying dark once the screen is off, see [`crate::sleep`].
+    deep_sleep: bool,
+    battery_limits: Limits,
+    battery_low: bool,
+    /// C is held past a long press, see [`POWER_OFF_HINT_MS`].
+    long_c: bool,
+    /// Readings in a row at or below [`Limits::critical_mv`].
+    battery_critical: u8,
 }
 
 impl App {
-    pub fn new() -> Self {
-        let sender = EVENTS.publisher().unwrap();
-        let receiver = EVENTS.subscriber().unwrap();
+    pub fn new(role: Role) -> Self {
+        let sender = events::sender();
+        let receiver = events::receiver();
         Self {
             sender,
             receiver,
-            exit: false,
+            exit: None,
+            role,
             layout: AppLayout::new(Rect::default()),
-            c_start: None,
-            b_start: None,
-            selected_tab: SelectedTab::Info,
+            input: Recognizer::default(),
+            idle: IdleTimer::new(idle::DIM_AFTER, idle::OFF_AFTER, Instant::now()),
+            overlays: Overlays::default(),
+            screens: screens::registry(),
+            selected: 0,
             tab_touched: false,
-            #[cfg(feature = "tv")]
-            tv: TVState {
-                current_btn: events::Remote::OnOff,
-            },
-            stats: Stats::default(),
             hat: None,
+            deep_sleep: false,
+            battery_limits: Limits::default(),
+            battery_low: false,
+            long_c: false,
+            battery_critical: 0,
+        }
+    }
+
+    pub fn with_battery_limits(mut self, limits: Limits) -> Self {
+        self.battery_limits = limits;
+        self
+    }
+
+    pub fn with_deep_sleep(mut self) -> Self {
+        self.deep_sleep = true;
+        self
+    }
+
+    /// Comes back to where the app was before the deep sleep.
+    pub fn with_snapshot(mut self, snapshot: &Snapshot) -> Self {
+        if let Some(index) = self.tab_index(&snapshot.tab) {
+            self.select_tab(index);
+        }
+        for screen in self.screens.iter_mut() {
+            screen.restore(snapshot);
+        }
+        self
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let mut snapshot = Snapshot {
+            tab: self.screens[self.selected].title().into(),
+            remote: None,
+        };
+        for screen in self.screens.iter() {
+            screen.save(&mut snapshot);
         }
+        snapshot
     }
 
-    pub async fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), B::Error> {
+    pub async fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<Exit, B::Error> {
         log::info!("Application started");
         self.layout = AppLayout::new(terminal.get_frame().area());
 
-        while !self.exit {
+        let exit = loop {
+            if let Some(exit) = self.exit {
+                break exit;
+            }
+            self.overlays.expire(Instant::now());
             terminal.draw(|frame| self.draw(frame))?;
 
-            let msg = self.receiver.next_message_pure().await;
+            let deadline = [self.idle.deadline(), self.overlays.deadline()]
+                .into_iter()
+                .flatten()
+                .min()
+                .unwrap_or(Instant::MAX);
+            if let Ok(msg) = with_deadline(deadline, self.receiver.next_message_pure()).await {
+                self.handle_events(msg).await;
+            }
+
+            if let Some(state) = self.idle.poll(Instant::now()) {
+                self.set_idle(state).await;
+            }
+        };
 
-            self.handle_events(msg).await;
+        // the last frame, it stays up until the power is gone
+        if exit == Exit::PowerOff {
+            terminal.draw(draw_goodbye)?;
         }
 
-        Ok(())
+        Ok(exit)
     }
 
     fn next_tab(&mut self) {
-        #[cfg(feature = "tv")]
-        {
-            self.tv.current_btn = events::Remote::OnOff;
-        }
-        self.selected_tab = self.selected_tab.next();
-        self.tab_touched = false;
+        self.step_tab(1);
     }
 
-    #[allow(unused)]
     fn prev_tab(&mut self) {
-        self.selected_tab = self.selected_tab.prev();
+        self.step_tab(self.screens.len() - 1);
+    }
+
+    /// Moves `by` tabs forward, skipping the ones not enabled in this role.
+    fn step_tab(&mut self, by: usize) {
+        let len = self.screens.len();
+        let mut index = self.selected;
+        loop {
+            index = (index + by) % len;
+            if self.screens[index].enabled(self.role) || index == self.selected {
+                break;
+            }
+        }
+        self.select_tab(index);
+    }
+
+    /// Enabled tabs only.
+    fn tab_index(&self, title: &str) -> Option<usize> {
+        self.screens
+            .iter()
+            .position(|screen| screen.enabled(self.role) && screen.title() == title)
+    }
+
+    fn select_tab(&mut self, index: usize) {
+        self.selected = index;
         self.tab_touched = false;
+        self.screens[index].selected();
     }
 
     fn touch_tab(&mut self) {
         self.tab_touched = true;
     }
 
-    fn c_held_time(&self) -> u64 {
-        if let Some(start) = self.c_start {
-            let elapsed = start.elapsed();
-            let held_ms = elapsed.as_millis();
-            return held_ms;
-        }
-
-        0
+    fn held_time(&self, button: Button) -> u64 {
+        self.input
+            .held(button, Instant::now())
+            .map(|held| held.as_millis())
+            .unwrap_or(0)
     }
 
-    #[allow(unused)]
-    fn b_held_time(&self) -> u64 {
-        if let Some(start) = self.b_start {
-            let elapsed = start.elapsed();
-            let held_ms = elapsed.as_millis();
-            return held_ms;
-        }
-
-        0
+    fn context(&self) -> Context {
+        Context::new(
+            self.role,
+            self.hat,
+            self.tab_touched,
+            self.input.thresholds(),
+            self.held_time(Button::B),
+            self.held_time(Button::C),
+        )
     }
 
     fn draw(&self, frame: &mut Frame) {
@@ -131,36 +223,36 @@ impl App {
         } = self.layout;
 
         let buf = frame.buffer_mut();
+        let ctx = self.context();
 
         self.draw_tabs(header, buf);
 
-        match self.selected_tab {
-            #[cfg(feature = "controller")]
-            SelectedTab::Controller => self.draw_controller(main, buf),
-            #[cfg(feature = "vehicle")]
-            SelectedTab::Vehicle => self.draw_vehicle(main, buf),
-            #[cfg(feature = "tv")]
-            SelectedTab::Remote => self.draw_remote(main, buf),
-            SelectedTab::Info => self.draw_info(main, buf),
-            SelectedTab::Dev => self.draw_dev(main, buf),
-        }
+        self.screens[self.selected].render(main, buf, &ctx);
+        self.overlays.render(main, buf);
 
-        self.draw_footer(footer, buf);
+        self.draw_footer(footer, buf, &ctx);
     }
 
     fn draw_tabs(&self, area: Rect, buf: &mut Buffer) {
-        let titles = SelectedTab::titles();
-        let selected_tab_index = self.selected_tab as usize;
-
-        let bg_color = Color::Rgb(
-            if self.c_held_time() < 500 {
-                50
-            } else {
-                ms_to_red(self.c_held_time())
-            },
-            50,
-            50,
-        );
+        let titles = self
+            .screens
+            .iter()
+            .filter(|screen| screen.enabled(self.role))
+            .map(|screen| screen.title())
+            .collect::<Vec<_>>();
+        let selected_tab_index = self.screens[..self.selected]
+            .iter()
+            .filter(|screen| screen.enabled(self.role))
+            .count();
+
+        let long_press = self.input.thresholds().long_press.as_millis();
+        let bg_color = if self.held_time(Button::C) >= long_press {
+            Color::Rgb(ms_to_red(self.held_time(Button::C)), 50, 50)
+        } else if self.battery_low {
+            LOW_BATTERY_BG
+        } else {
+            Color::Rgb(50, 50, 50)
+        };
 
         Tabs::new(titles)
             .highlight_style(Style::new().fg(Color::White))
@@ -174,294 +266,301 @@ impl App {
                     .padding(Padding::left(1)),
             )
             .render(area, buf);
-    }
 
-    #[cfg(feature = "tv")]
-    fn draw_remote(&self, area: Rect, buf: &mut Buffer) {
-        use ratatui::prelude::StatefulWidget;
-        TVRemote::new().render(area, buf, &mut self.tv.clone());
+        if let Some(now) = clock::now_local() {
+            let time = format!("{:02}:{:02}", now.hour, now.minute);
+            buf.set_string(
+                area.right().saturating_sub(time.len() as u16 + 1),
+                area.y,
+                &time,
+                Style::new().fg(Color::Gray),
+            );
+        }
     }
 
-    #[cfg(feature = "controller")]
-    fn draw_controller(&self, area: Rect, buf: &mut Buffer) {
-        // TODO
-        Paragraph::new("Vehicle Controller")
-            .centered()
-            .block(Block::new().padding(Padding::top(1)))
-            .render(area, buf);
-    }
+    fn draw_footer(&self, area: Rect, buf: &mut Buffer, ctx: &Context) {
+        let info = if let Some(info) = self.overlays.footer() {
+            info.into()
+        } else if self.held_time(Button::C) > POWER_OFF_HINT_MS {
+            let left = POWER_OFF_HOLD_MS.saturating_sub(self.held_time(Button::C));
+            format!(" off in {}s, release to cancel", left.div_ceil(1000))
+        } else if let Some(info) = self.screens[self.selected].footer(ctx) {
+            info
+        } else {
+            return;
+        };
 
-    #[cfg(feature = "vehicle")]
-    fn draw_vehicle(&self, area: Rect, buf: &mut Buffer) {
-        // TODO
-        Paragraph::new("Vehicle")
-            .centered()
-            .block(Block::new().padding(Padding::top(1)))
-            .render(area, buf);
+        buf.set_string(
+            0,
+            area.bottom().saturating_sub(1),
+            &info,
+            Style::new().fg(Color::Gray),
+        );
     }
 
-    fn draw_dev(&self, area: Rect, buf: &mut Buffer) {
-        let log_lines = logger::latest_log_lines(area.height as usize);
-        let log_lines = log_lines
-            .iter()
-            .rev()
-            .map(|(level, msg)| {
-                let color = match level {
-                    Level::Error => Color::Red,
-                    Level::Warn => Color::Yellow,
-                    Level::Info => Color::White,
-                    Level::Debug => Color::DarkGray,
-                    Level::Trace => Color::Magenta,
-                };
-
-                Line::styled(msg, Style::new().fg(color))
-            })
-            .collect::<Vec<_>>();
+    /// Hands a joystick event or gesture to the selected screen, `true` if it used it.
+    async fn send_to_screen(&mut self, event: &Event) -> bool {
+        let mut ctx = self.context();
+        let handled = self.screens[self.selected].handle_event(event, &mut ctx);
+        for event in ctx.take_outbox() {
+            self.sender.publish(event).await;
+        }
+        handled
+    }
 
-        Paragraph::new(log_lines)
-            .wrap(Wrap { trim: true })
-            .render(area, buf);
+    /// Takes the page or PIN on top off the screen, if there is one.
+    async fn dismiss_overlay(&mut self) -> bool {
+        match self.overlays.dismiss() {
+            Some(Dismissed::Page) => self.sender.publish(Event::PageRead).await,
+            Some(Dismissed::Pin) => auth::cancel_pairing(),
+            None => return false,
+        }
+        // whatever is still held started out dismissing
+        self.input.cancel();
+        true
     }
 
-    fn draw_info(&self, area: Rect, buf: &mut Buffer) {
-        let info = [
-            ("battery", format!("{}%", self.stats.battery_level)),
-            (
-                "hat",
-                self.hat
-                    .map(|h| format!("{:?}", h))
-                    .unwrap_or("-".to_string()),
-            ),
-            (
-                "heap",
-                format!(
-                    "{}/{}",
-                    self.stats.heap_used / 1024,
-                    (self.stats.heap_used + self.stats.heap_free) / 1024
-                ),
-            ),
-        ];
-        let horizontal = Layout::horizontal([Constraint::Max(10), Constraint::Fill(1)]);
-        let vertical = Layout::vertical((0..info.len()).map(|_| Constraint::Length(1)));
-
-        let rows = vertical.split(area.inner(Margin::new(1, 1)));
-
-        let info_style = Style::new().fg(Color::DarkGray);
-
-        for (row, (title, value)) in rows.iter().cloned().zip(info) {
-            let [title_area, value_area] = horizontal.areas(row);
-
-            Paragraph::new(title)
-                .style(info_style)
-                .render(title_area, buf);
-            Paragraph::new(value).render(value_area, buf);
+    /// Hands a button or joystick move to the open dialog, `true` while there is one.
+    async fn answer_confirm(&mut self, event: &Event) -> bool {
+        if !self.overlays.modal() {
+            return false;
+        }
+        let yes = match event {
+            Event::Gesture(Gesture::Click(Button::A)) | Event::JoyC(events::JoyC::Button) => true,
+            Event::Gesture(Gesture::Click(Button::B | Button::C))
+            | Event::JoyC(events::JoyC::Arrow(events::JoycDirection::Left)) => false,
+            // the rest only doesn't go through
+            _ => return true,
+        };
+        if let Some(action) = self.overlays.answer(yes) {
+            self.sender.publish(action).await;
         }
+        true
     }
 
-    fn draw_footer(&self, area: Rect, buf: &mut Buffer) {
-        if self.c_held_time() > 1000 {
-            buf.set_string(
-                0,
-                area.bottom().saturating_sub(1),
-                " hold to shut down...",
-                Style::new().fg(Color::Gray),
+    /// Warns once the battery gets low and powers off before it's empty, not on USB though.
+    async fn check_battery(&mut self, stats: &Stats, now: Instant) {
+        let on_battery = stats.power == PowerSource::Battery;
+        let low = on_battery && stats.battery_level <= self.battery_limits.warn_level;
+        if low && !self.battery_low {
+            log::warn!("battery low: {}%", stats.battery_level);
+            self.overlays
+                .toast(format!("battery low, {}%", stats.battery_level), now);
+        }
+        self.battery_low = low;
+
+        // 0 is no reading at all
+        if on_battery && stats.battery_mv > 0 && stats.battery_mv <= self.battery_limits.critical_mv
+        {
+            self.battery_critical = self.battery_critical.saturating_add(1);
+            log::warn!(
+                "battery critical: {} mV ({}/{})",
+                stats.battery_mv,
+                self.battery_critical,
+                CRITICAL_READINGS
             );
-            return;
+        } else {
+            self.battery_critical = 0;
+        }
+        if self.battery_critical >= CRITICAL_READINGS {
+            log::warn!("battery critical, powering off");
+            self.sender.publish(Event::PowerOff).await;
         }
+    }
 
-        match self.selected_tab {
-            #[cfg(feature = "tv")]
-            SelectedTab::Remote if !matches!(self.hat, Some(events::StickHat::MiniJoyC)) => {
-                let c_mode = {
-                    let mut mode = "c - next tab";
-
-                    if self.tab_touched {
-                        if self.c_held_time() < 500 {
-                            mode = "c - prev btn";
-                        }
-                    }
-
-                    mode
-                };
-
-                let b_mode = {
-                    let mut mode = "b - next btn";
-
-                    if self.b_held_time() > 300 {
-                        mode = "b - next row"
-                    }
-
-                    mode
-                };
-
-                let btns = [c_mode, b_mode]
-                    .iter()
-                    .map(|s| s.to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-
-                let info = format!(" {}", btns);
-
-                buf.set_string(
-                    0,
-                    area.bottom().saturating_sub(1),
-                    &info,
-                    Style::new().fg(Color::Gray),
-                );
-            }
-            _ => {}
+    async fn set_idle(&mut self, state: IdleState) {
+        log::debug!("idle: {:?}", state);
+        idle::set_state(state);
+        self.sender.publish(Event::Idle(state)).await;
+        if state == IdleState::Off && self.deep_sleep {
+            log::info!("deep sleep");
+            self.sender.publish(Event::DeepSleep).await;
         }
     }
 
     async fn handle_events(&mut self, event: Event) {
+        let now = Instant::now();
+        if is_input(&event) || wakes_screen(&event) {
+            let input = self.idle.input(now);
+            if input != idle::Input::Used {
+                self.set_idle(IdleState::Active).await;
+            }
+            // the press that wakes the screen does nothing else, its release neither
+            if input == idle::Input::Woke && is_input(&event) {
+                self.input.cancel();
+                return;
+            }
+        }
+
+        for screen in self.screens.iter_mut() {
+            screen.update(&event);
+        }
+
+        let gesture = match event {
+            Event::ButtonDown(button) => self.input.press(button, now),
+            // let go before the power-off countdown shows, the long press was for the tabs
+            Event::ButtonUp(Button::C) if self.long_c => {
+                self.long_c = false;
+                let early = self
+                    .input
+                    .held(Button::C, now)
+                    .is_some_and(|held| held.as_millis() < POWER_OFF_HINT_MS);
+                self.input.release(Button::C, now);
+                early.then_some(Gesture::LongPress(Button::C))
+            }
+            Event::ButtonUp(button) => self.input.release(button, now),
+            // `Draw` comes often enough while a button is held
+            _ => match self.input.poll(now) {
+                // holding C counts down to power off, nothing else comes of it until it's let go
+                Some(Gesture::LongPress(Button::C)) => {
+                    self.long_c = true;
+                    None
+                }
+                Some(Gesture::Repeat(Button::C)) => None,
+                gesture => gesture,
+            },
+        };
+
+        if self.held_time(Button::C) >= POWER_OFF_HOLD_MS {
+            log::info!("powering off");
+            // nothing more comes of this press
+            self.input.cancel();
+            self.sender.publish(Event::PowerOff).await;
+        }
+
+        if let Event::JoyC(_) = event {
+            // a dialog takes the joystick, the first move while an overlay is up only dismisses it
+            let taken = self.answer_confirm(&event).await
+                || (dismisses_overlay(&event) && self.dismiss_overlay().await);
+            if !taken {
+                self.touch_tab();
+                self.send_to_screen(&event).await;
+            }
+        }
+
         match event {
             Event::InitHat(hat) => {
                 self.hat = Some(hat);
+                self.overlays.toast(format!("{:?} connected", hat), now);
             }
-            #[allow(unused)]
-            Event::JoyC(joyc_event) => {
-                self.touch_tab();
-                match self.selected_tab {
-                    #[cfg(feature = "controller")]
-                    SelectedTab::Controller => match joyc_event {
-                        crate::JoyC::Pos((x, y)) => {
-                            use crate::Controller;
-
-                            let x = x as i16;
-                            let y = y as i16;
-
-                            let left = (y + x).clamp(-100, 100) as i8;
-                            let right = (y - x).clamp(-100, 100) as i8;
-
-                            self.sender
-                                .publish(Event::Controller(Controller::Move(left, right)))
-                                .await;
-                        }
-                        _ => {}
-                    },
-                    #[cfg(feature = "tv")]
-                    SelectedTab::Remote => match joyc_event {
-                        crate::JoyC::Button => {
-                            self.sender
-                                .publish(Event::Remote(self.tv.current_btn))
-                                .await
-                        }
-                        crate::JoyC::Arrow(dir) => match dir {
-                            crate::JoycDirection::Up => {
-                                self.tv.prev_row();
-                            }
-                            crate::JoycDirection::Right => {
-                                self.tv.next_btn();
-                            }
-                            crate::JoycDirection::Down => {
-                                self.tv.next_row();
-                            }
-                            crate::JoycDirection::Left => {
-                                self.tv.prev_btn();
-                            }
-                            crate::JoycDirection::Center => {}
-                        },
-                        _ => {}
-                    },
-                    _ => {}
-                };
+            Event::StatsUpdated(ref stats) => {
+                self.check_battery(stats, now).await;
             }
-            Event::ButtonUp(events::Button::A) => match self.selected_tab {
-                #[cfg(feature = "tv")]
-                SelectedTab::Remote => {
-                    self.touch_tab();
-                    self.sender
-                        .publish(Event::Remote(self.tv.current_btn))
-                        .await
-                }
-                _ => {}
-            },
-            Event::ButtonDown(events::Button::B) => {
-                if self.b_start.is_none() {
-                    self.b_start = Some(Instant::now());
-                }
+            Event::Toast(ref text) => {
+                self.overlays.toast(text.clone(), now);
             }
-            Event::ButtonUp(events::Button::B) => {
-                match self.selected_tab {
-                    #[cfg(feature = "tv")]
-                    SelectedTab::Remote => {
-                        self.touch_tab();
-                        if self.b_held_time() > 300 {
-                            self.tv.next_row();
-                        } else {
-                            self.tv.next_btn();
-                        }
-                    }
-                    _ => {}
-                };
-                self.b_start = None;
+            Event::Confirm(ref confirm) => {
+                self.overlays.confirm(confirm.clone());
             }
-            Event::ButtonDown(events::Button::C) => {
-                if self.c_start.is_none() {
-                    self.c_start = Some(Instant::now());
+            Event::Page(ref page) => {
+                self.overlays.page(page.clone());
+            }
+            Event::PairingPin(ref pin) => {
+                self.overlays.pin(pin.clone(), now);
+            }
+            Event::PowerOff => {
+                self.exit = Some(Exit::PowerOff);
+            }
+            Event::DeepSleep => {
+                self.exit = Some(Exit::DeepSleep);
+            }
+            Event::SelectTab(ref title) => {
+                if let Some(index) = self.tab_index(title) {
+                    self.select_tab(index);
                 }
             }
-            Event::ButtonUp(events::Button::C) => {
-                if matches!(self.hat, Some(events::StickHat::MiniJoyC)) {
+            _ => {}
+        }
+
+        if let Some(gesture) = gesture {
+            self.handle_gesture(gesture).await;
+        }
+    }
+
+    async fn handle_gesture(&mut self, gesture: Gesture) {
+        if self.answer_confirm(&Event::Gesture(gesture)).await {
+            return;
+        }
+        // the first button while an overlay is up only dismisses it
+        if self.dismiss_overlay().await {
+            return;
+        }
+
+        match gesture {
+            Gesture::Click(Button::C) | Gesture::DoubleClick(Button::C) => {
+                if matches!(self.hat, Some(events::StickHat::MiniJoyC)) || !self.tab_touched {
                     self.next_tab();
-                } else if self.tab_touched {
-                    if self.c_held_time() > 500 {
-                        self.next_tab();
-                    } else {
-                        match self.selected_tab {
-                            #[cfg(feature = "tv")]
-                            SelectedTab::Remote => {
-                                self.tv.prev_btn();
-                            }
-                            _ => {}
-                        }
-                    }
                 } else {
-                    self.next_tab();
+                    self.send_gesture(gesture).await;
                 }
-                self.c_start = None;
             }
-            Event::StatsUpdated(stats) => {
-                self.stats = stats;
+            Gesture::LongPress(Button::C) => self.next_tab(),
+            Gesture::Chord(Button::A, Button::B) | Gesture::Chord(Button::B, Button::A) => {
+                self.prev_tab()
             }
-            _ => {}
+            _ => {
+                if self.send_gesture(gesture).await {
+                    self.touch_tab();
+                }
+            }
+        }
+    }
+
+    async fn send_gesture(&mut self, gesture: Gesture) -> bool {
+        if self.send_to_screen(&Event::Gesture(gesture)).await {
+            return true;
+        }
+        // a double click nobody wants is another click
+        if let Gesture::DoubleClick(button) = gesture {
+            return self
+                .send_to_screen(&Event::Gesture(Gesture::Click(button)))
+                .await;
         }
+        false
     }
 }
 
-#[derive(
-    Clone, Copy, PartialEq, strum::EnumIter, strum::EnumCount, strum::FromRepr, strum::Display,
-)]
-pub enum SelectedTab {
-    #[strum(to_string = "info")]
-    Info,
-    #[cfg(feature = "controller")]
-    #[strum(to_string = "ctrl")]
-    Controller,
-    #[cfg(feature = "vehicle")]
-    #[strum(to_string = "vehicle")]
-    Vehicle,
-    #[cfg(feature = "tv")]
-    #[strum(to_string = "tv")]
-    Remote,
-    #[strum(to_string = "dev")]
-    Dev,
+/// Buttons and the joystick, what keeps the screen on.
+fn is_input(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::ButtonDown(_) | Event::ButtonUp(_) | Event::JoyC(_)
+    )
 }
 
-impl SelectedTab {
-    pub fn next(self) -> Self {
-        Self::from_repr((self as usize + 1) % Self::iter().len()).unwrap()
-    }
+/// Something to look at came in, the screen lights up for it.
+fn wakes_screen(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Page(_) | Event::PairingPin(Some(_)) | Event::Confirm(_)
+    )
+}
 
-    pub fn prev(self) -> Self {
-        let len = Self::iter().len();
-        Self::from_repr((self as usize + len - 1) % len).unwrap()
-    }
+/// Joystick moves, what takes an overlay off the screen besides the buttons.
+fn dismisses_overlay(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::JoyC(
+            events::JoyC::Button
+                | events::JoyC::Arrow(
+                    events::JoycDirection::Up
+                        | events::JoycDirection::Right
+                        | events::JoycDirection::Down
+                        | events::JoycDirection::Left
+                )
+        )
+    )
+}
 
-    pub fn titles() -> Vec<String> {
-        Self::iter().map(|t| t.to_string()).collect::<Vec<_>>()
-    }
+fn draw_goodbye(frame: &mut Frame) {
+    let area = frame.area();
+    Paragraph::new(vec![
+        Line::styled("bye", Style::new().fg(Color::White).bold()),
+        Line::styled("powering off", Style::new().fg(Color::DarkGray)),
+    ])
+    .centered()
+    .block(Block::new().padding(Padding::top(area.height.saturating_sub(2) / 2)))
+    .render(area, frame.buffer_mut());
 }
 
 /// 0 -> 30, 3000 -> 255
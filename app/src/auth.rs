@@ -0,0 +1,483 @@
+//! Pairing hosts with the stick, so not everyone on the AP can fire IR or drive servos.
+//!
+//! A host asks to pair and gets a nonce, the stick shows a one-time PIN and the host answers
+//! with [`proof`] of it. That earns a session: a token naming it, and a [`session_key`] both
+//! sides derive from the nonce and the PIN, which never goes over the air. Requests carry an
+//! [`hmac`] over a counter that only goes up and the request itself, see
+//! [`crate::rpc::authenticate`]. Sessions last until they're revoked in the auth tab or the
+//! stick reboots.
+//!
+//! Pair on a network you trust. The AP is open, so anyone on it who captures a pairing's
+//! nonce and proof can try all 10^6 PINs against them offline, which takes seconds, and then
+//! derive the session key and sign requests as the host. PIN retries and the timeout only
+//! slow down guessing online. Sessions paired where nobody was listening stay safe to use on
+//! the open AP afterwards.
+//!
+//! The state is global so every transport shares the same clients, the platform provides
+//! randomness with [`init`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::{cell::RefCell, fmt};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use critical_section::Mutex;
+use embassy_time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub type Token = [u8; 16];
+pub type Nonce = [u8; 16];
+pub type Proof = [u8; 32];
+pub type Key = [u8; 32];
+pub type Mac = [u8; 32];
+
+/// The PIN is only good for this long.
+pub const PIN_TIMEOUT: Duration = Duration::from_secs(60);
+/// Wrong PINs before pairing has to start over.
+pub const MAX_ATTEMPTS: u8 = 3;
+/// The one seen longest ago is dropped for a new one.
+pub const MAX_CLIENTS: usize = 8;
+pub const MAX_NAME_LEN: usize = 16;
+
+static AUTH: Mutex<RefCell<Auth>> = Mutex::new(RefCell::new(Auth::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AuthError {
+    /// No token, one that was revoked, or a request that doesn't match its MAC.
+    Unauthorized,
+    /// The counter didn't go up, the request was seen before.
+    Replayed,
+    /// Another host is pairing, try again once its PIN is used or expired.
+    Busy,
+    NotPairing,
+    PinExpired,
+    WrongPin,
+    /// The platform has no randomness for PINs and tokens.
+    Unavailable,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Unauthorized => write!(f, "not paired"),
+            AuthError::Replayed => write!(f, "request replayed"),
+            AuthError::Busy => write!(f, "another host is pairing"),
+            AuthError::NotPairing => write!(f, "no pairing in progress"),
+            AuthError::PinExpired => write!(f, "PIN expired"),
+            AuthError::WrongPin => write!(f, "wrong PIN"),
+            AuthError::Unavailable => write!(f, "pairing not available"),
+        }
+    }
+}
+
+/// What the display shows while a host is pairing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairingPin {
+    pub client: String,
+    /// Six digits.
+    pub pin: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Client {
+    pub name: String,
+    token: Token,
+    key: Key,
+    /// Of the last request, the next one needs a higher one.
+    counter: u64,
+    pub paired: Instant,
+    pub last_seen: Instant,
+}
+
+struct Pairing {
+    client: String,
+    pin: u32,
+    nonce: Nonce,
+    started: Instant,
+    attempts: u8,
+}
+
+struct Auth {
+    random: Option<fn(&mut [u8])>,
+    pairing: Option<Pairing>,
+    clients: Vec<Client>,
+}
+
+impl Auth {
+    const fn new() -> Self {
+        Self {
+            random: None,
+            pairing: None,
+            clients: Vec::new(),
+        }
+    }
+
+    fn begin_pairing(
+        &mut self,
+        client: &str,
+        now: Instant,
+    ) -> Result<(Nonce, PairingPin), AuthError> {
+        let random = self.random.ok_or(AuthError::Unavailable)?;
+        // a request can't take the PIN off the screen from under the host that asked for it
+        if self
+            .pairing
+            .as_ref()
+            .is_some_and(|pairing| now.saturating_duration_since(pairing.started) <= PIN_TIMEOUT)
+        {
+            return Err(AuthError::Busy);
+        }
+        let mut nonce = Nonce::default();
+        random(&mut nonce);
+        let mut pin = [0u8; 4];
+        random(&mut pin);
+        let pin = u32::from_le_bytes(pin) % 1_000_000;
+
+        let client: String = client.chars().take(MAX_NAME_LEN).collect();
+        self.pairing = Some(Pairing {
+            client: client.clone(),
+            pin,
+            nonce,
+            started: now,
+            attempts: 0,
+        });
+        Ok((nonce, PairingPin { client, pin }))
+    }
+
+    fn pair(&mut self, answer: &Proof, now: Instant) -> Result<(String, Token), AuthError> {
+        let random = self.random.ok_or(AuthError::Unavailable)?;
+        let pairing = self.pairing.as_mut().ok_or(AuthError::NotPairing)?;
+        if now.saturating_duration_since(pairing.started) > PIN_TIMEOUT {
+            self.pairing = None;
+            return Err(AuthError::PinExpired);
+        }
+        if !equal(answer, &proof(&pairing.nonce, pairing.pin)) {
+            pairing.attempts += 1;
+            if pairing.attempts >= MAX_ATTEMPTS {
+                self.pairing = None;
+            }
+            return Err(AuthError::WrongPin);
+        }
+
+        let Pairing {
+            client, pin, nonce, ..
+        } = self.pairing.take().unwrap();
+        let mut token = Token::default();
+        random(&mut token);
+
+        self.clients.retain(|c| c.name != client);
+        if self.clients.len() >= MAX_CLIENTS
+            && let Some(oldest) = self
+                .clients
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, c)| c.last_seen)
+                .map(|(i, _)| i)
+        {
+            self.clients.remove(oldest);
+        }
+        self.clients.push(Client {
+            name: client.clone(),
+            token,
+            key: session_key(&nonce, pin),
+            counter: 0,
+            paired: now,
+            last_seen: now,
+        });
+        Ok((client, token))
+    }
+
+    fn check(
+        &mut self,
+        token: &Token,
+        counter: u64,
+        mac: &Mac,
+        request: &[u8],
+        now: Instant,
+    ) -> Result<String, AuthError> {
+        let client = self
+            .clients
+            .iter_mut()
+            .find(|c| equal(&c.token, token))
+            .ok_or(AuthError::Unauthorized)?;
+        if !equal(mac, &hmac(&client.key, counter, request)) {
+            return Err(AuthError::Unauthorized);
+        }
+        if counter <= client.counter {
+            return Err(AuthError::Replayed);
+        }
+        client.counter = counter;
+        client.last_seen = now;
+        Ok(client.name.clone())
+    }
+
+    fn revoke(&mut self, name: &str) -> bool {
+        let len = self.clients.len();
+        self.clients.retain(|c| c.name != name);
+        self.clients.len() != len
+    }
+}
+
+/// What a host sends to show it knows `pin`.
+pub fn proof(nonce: &Nonce, pin: u32) -> Proof {
+    let mut hasher = Sha256::new();
+    hasher.update(nonce);
+    hasher.update(pin.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// The key a session's requests are signed with, derived from the pairing on both sides.
+pub fn session_key(nonce: &Nonce, pin: u32) -> Key {
+    let mut hasher = Sha256::new();
+    hasher.update(b"stick session key");
+    hasher.update(nonce);
+    hasher.update(pin.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// HMAC-SHA256 of a request and its counter.
+pub fn hmac(key: &Key, counter: u64, request: &[u8]) -> Mac {
+    const BLOCK: usize = 64;
+    let mut ipad = [0x36; BLOCK];
+    let mut opad = [0x5c; BLOCK];
+    for (i, byte) in key.iter().enumerate() {
+        ipad[i] ^= byte;
+        opad[i] ^= byte;
+    }
+    let mut inner = Sha256::new();
+    inner.update(ipad);
+    inner.update(counter.to_be_bytes());
+    inner.update(request);
+    let mut outer = Sha256::new();
+    outer.update(opad);
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Without an early return, so the time taken doesn't tell how much of a guess was right.
+fn equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// `random` fills a buffer from the platform's RNG, pairing is refused until it's set.
+pub fn init(random: fn(&mut [u8])) {
+    critical_section::with(|cs| AUTH.borrow_ref_mut(cs).random = Some(random));
+}
+
+/// Starts pairing `client`, the nonce goes back to the host and the PIN on screen. Refused
+/// while another PIN is still good.
+pub fn begin_pairing(client: &str) -> Result<(Nonce, PairingPin), AuthError> {
+    critical_section::with(|cs| {
+        AUTH.borrow_ref_mut(cs)
+            .begin_pairing(client, Instant::now())
+    })
+}
+
+/// Checks the host's proof of the PIN, a new session token if it's right. Pairing ends with
+/// the right PIN, the last wrong one or an expired one.
+pub fn pair(proof: &Proof) -> Result<(String, Token), AuthError> {
+    critical_section::with(|cs| AUTH.borrow_ref_mut(cs).pair(proof, Instant::now()))
+}
+
+pub fn is_pairing() -> bool {
+    critical_section::with(|cs| AUTH.borrow_ref(cs).pairing.is_some())
+}
+
+/// Drops the pairing on screen, e.g. when the user turns it down.
+pub fn cancel_pairing() {
+    critical_section::with(|cs| AUTH.borrow_ref_mut(cs).pairing = None);
+}
+
+/// The client holding `token`, if `mac` is its [`hmac`] over `counter` and `request` and the
+/// counter went up since its last request.
+pub fn check(token: &Token, counter: u64, mac: &Mac, request: &[u8]) -> Result<String, AuthError> {
+    critical_section::with(|cs| {
+        AUTH.borrow_ref_mut(cs)
+            .check(token, counter, mac, request, Instant::now())
+    })
+}
+
+/// Ends the session of `name`, `true` if there was one.
+pub fn revoke(name: &str) -> bool {
+    critical_section::with(|cs| AUTH.borrow_ref_mut(cs).revoke(name))
+}
+
+/// Paired clients, oldest first.
+pub fn clients() -> Vec<Client> {
+    critical_section::with(|cs| AUTH.borrow_ref(cs).clients.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    /// Every call fills the buffer with a different pattern, so tokens differ.
+    fn random(buf: &mut [u8]) {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        let next = NEXT.fetch_add(1, Ordering::Relaxed).to_le_bytes();
+        for (byte, value) in buf.iter_mut().zip(next.iter().cycle()) {
+            *byte = *value;
+        }
+    }
+
+    fn auth() -> Auth {
+        let mut auth = Auth::new();
+        auth.random = Some(random);
+        auth
+    }
+
+    /// Pairs `name` at `at` seconds, its token and key.
+    fn paired(auth: &mut Auth, name: &str, at: u64) -> (Token, Key) {
+        let now = Instant::from_secs(at);
+        let (nonce, pin) = auth.begin_pairing(name, now).unwrap();
+        let (_, token) = auth.pair(&proof(&nonce, pin.pin), now).unwrap();
+        (token, session_key(&nonce, pin.pin))
+    }
+
+    fn check(auth: &mut Auth, token: &Token, key: &Key, counter: u64) -> Result<String, AuthError> {
+        let mac = hmac(key, counter, b"stats");
+        auth.check(token, counter, &mac, b"stats", Instant::from_secs(100))
+    }
+
+    #[test]
+    fn pairs_with_the_pin() {
+        let mut auth = auth();
+        let (token, key) = paired(&mut auth, "laptop", 0);
+        assert!(auth.pairing.is_none());
+        assert_eq!(check(&mut auth, &token, &key, 1), Ok("laptop".into()));
+    }
+
+    #[test]
+    fn wrong_pin_until_attempts_run_out() {
+        let mut auth = auth();
+        let (nonce, pin) = auth.begin_pairing("laptop", Instant::from_secs(0)).unwrap();
+        let wrong = proof(&nonce, (pin.pin + 1) % 1_000_000);
+        for _ in 0..MAX_ATTEMPTS {
+            assert_eq!(
+                auth.pair(&wrong, Instant::from_secs(1)),
+                Err(AuthError::WrongPin)
+            );
+        }
+        // the right PIN is too late now
+        let right = proof(&nonce, pin.pin);
+        assert_eq!(
+            auth.pair(&right, Instant::from_secs(2)),
+            Err(AuthError::NotPairing)
+        );
+    }
+
+    #[test]
+    fn pin_expires() {
+        let mut auth = auth();
+        let (nonce, pin) = auth.begin_pairing("laptop", Instant::from_secs(0)).unwrap();
+        let late = Instant::from_secs(0) + PIN_TIMEOUT + Duration::from_secs(1);
+        assert_eq!(
+            auth.pair(&proof(&nonce, pin.pin), late),
+            Err(AuthError::PinExpired)
+        );
+        assert!(auth.pairing.is_none());
+    }
+
+    #[test]
+    fn busy_while_a_pin_is_good() {
+        let mut auth = auth();
+        auth.begin_pairing("laptop", Instant::from_secs(0)).unwrap();
+        assert_eq!(
+            auth.begin_pairing("phone", Instant::from_secs(30)).err(),
+            Some(AuthError::Busy)
+        );
+        // an expired PIN doesn't hold up the next host
+        let late = Instant::from_secs(0) + PIN_TIMEOUT + Duration::from_secs(1);
+        let (_, pin) = auth.begin_pairing("phone", late).unwrap();
+        assert_eq!(pin.client, "phone");
+    }
+
+    #[test]
+    fn no_pairing_without_randomness() {
+        let mut auth = Auth::new();
+        assert_eq!(
+            auth.begin_pairing("laptop", Instant::from_secs(0)).err(),
+            Some(AuthError::Unavailable)
+        );
+    }
+
+    #[test]
+    fn counter_has_to_go_up() {
+        let mut auth = auth();
+        let (token, key) = paired(&mut auth, "laptop", 0);
+        assert!(check(&mut auth, &token, &key, 5).is_ok());
+        assert_eq!(check(&mut auth, &token, &key, 5), Err(AuthError::Replayed));
+        assert_eq!(check(&mut auth, &token, &key, 4), Err(AuthError::Replayed));
+        assert!(check(&mut auth, &token, &key, 6).is_ok());
+    }
+
+    #[test]
+    fn bad_mac_or_token_is_unauthorized() {
+        let mut auth = auth();
+        let (token, key) = paired(&mut auth, "laptop", 0);
+        let now = Instant::from_secs(1);
+
+        let mac = hmac(&key, 1, b"stats");
+        assert_eq!(
+            auth.check(&token, 1, &mac, b"remote", now),
+            Err(AuthError::Unauthorized)
+        );
+        let other = hmac(&[0; 32], 1, b"stats");
+        assert_eq!(
+            auth.check(&token, 1, &other, b"stats", now),
+            Err(AuthError::Unauthorized)
+        );
+        assert_eq!(
+            auth.check(&[0; 16], 1, &mac, b"stats", now),
+            Err(AuthError::Unauthorized)
+        );
+        // a rejected request doesn't use up its counter
+        assert!(auth.check(&token, 1, &mac, b"stats", now).is_ok());
+    }
+
+    #[test]
+    fn revoked_client_is_unauthorized() {
+        let mut auth = auth();
+        let (token, key) = paired(&mut auth, "laptop", 0);
+        assert!(auth.revoke("laptop"));
+        assert!(!auth.revoke("laptop"));
+        assert_eq!(
+            check(&mut auth, &token, &key, 1),
+            Err(AuthError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn pairing_again_replaces_the_session() {
+        let mut auth = auth();
+        let (old, key) = paired(&mut auth, "laptop", 0);
+        paired(&mut auth, "laptop", 1);
+        assert_eq!(auth.clients.len(), 1);
+        assert_eq!(
+            check(&mut auth, &old, &key, 1),
+            Err(AuthError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn full_table_drops_the_one_seen_longest_ago() {
+        let mut auth = auth();
+        let mut sessions = Vec::new();
+        for i in 0..MAX_CLIENTS {
+            sessions.push(paired(&mut auth, &format!("host{i}"), i as u64));
+        }
+        // host0 paired first but was seen last
+        let (token, key) = sessions[0];
+        check(&mut auth, &token, &key, 1).unwrap();
+
+        paired(&mut auth, "new", 50);
+        let names: Vec<_> = auth.clients.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names.len(), MAX_CLIENTS);
+        assert!(names.contains(&"host0"));
+        assert!(!names.contains(&"host1"));
+        assert!(names.contains(&"new"));
+    }
+}
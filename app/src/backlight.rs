@@ -0,0 +1,18 @@
+//! Backlight brightness, the platform turns [`Event::SetBrightness`] into PWM or whatever
+//! it has.
+//!
+//! [`Event::SetBrightness`]: crate::events::Event::SetBrightness
+
+/// What the info tab steps through, in percent.
+pub const LEVELS: [u8; 5] = [10, 25, 50, 75, 100];
+/// At boot, before anything asks for another level.
+pub const DEFAULT: u8 = 100;
+
+/// The level after `percent`, the dimmest after the brightest.
+pub fn next_level(percent: u8) -> u8 {
+    LEVELS
+        .iter()
+        .copied()
+        .find(|level| *level > percent)
+        .unwrap_or(LEVELS[0])
+}
@@ -0,0 +1,355 @@
+//! Battery gauge out of raw voltage readings.
+//!
+//! The [`Gauge`] is fed millivolts and the time, like [`crate::input::Recognizer`], and
+//! never touches an ADC. It averages the last few readings, looks the level up on a LiPo
+//! discharge curve and guesses the power source from the voltage: a jump up that holds for a
+//! few readings is a charger plugged in, a jump down one pulled, and a slow climb is charging
+//! too. Time left comes
+//! from how fast the level dropped so far.
+
+use embassy_time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+/// Readings averaged.
+pub const WINDOW: usize = 8;
+/// A reading this far off the average is a charger plugged in or pulled.
+pub const EDGE_MV: u16 = 80;
+/// Readings in a row that jumped up before it's a charger, not a spike.
+pub const EDGE_READINGS: u8 = 3;
+/// The voltage trend and the discharge rate are taken over this long.
+pub const TREND_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// Climbing this much over [`TREND_WINDOW`] is charging.
+pub const RISE_MV: u16 = 15;
+/// Done charging once it stops climbing up here, the battery is full and on USB.
+pub const FULL_MV: u16 = 4150;
+
+/// When the app warns and when it shuts the stick down, only ever on battery.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// A toast and a red header from this level down, in percent.
+    pub warn_level: u8,
+    /// A clean shutdown at this voltage, before the cell cuts out on its own.
+    pub critical_mv: u16,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            warn_level: 15,
+            critical_mv: 3450,
+        }
+    }
+}
+
+/// Typical single cell LiPo at a light load, millivolts to percent, falling.
+const CURVE: [(u16, u8); 21] = [
+    (4200, 100),
+    (4150, 95),
+    (4110, 90),
+    (4080, 85),
+    (4020, 80),
+    (3980, 75),
+    (3950, 70),
+    (3910, 65),
+    (3870, 60),
+    (3850, 55),
+    (3840, 50),
+    (3820, 45),
+    (3800, 40),
+    (3790, 35),
+    (3770, 30),
+    (3750, 25),
+    (3730, 20),
+    (3710, 15),
+    (3690, 10),
+    (3610, 5),
+    (3300, 0),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, strum::IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PowerSource {
+    #[default]
+    Battery,
+    Charging,
+    /// Charged and still on USB.
+    Usb,
+}
+
+/// Battery level in percent for a resting voltage, between the points of the curve.
+pub fn level(mv: u16) -> u8 {
+    let (top_mv, top) = CURVE[0];
+    if mv >= top_mv {
+        return top;
+    }
+    CURVE
+        .windows(2)
+        .find(|pair| mv >= pair[1].0)
+        .map(|pair| {
+            let ((hi_mv, hi), (lo_mv, lo)) = (pair[0], pair[1]);
+            let span = (hi - lo) as u32 * (mv - lo_mv) as u32 / (hi_mv - lo_mv) as u32;
+            lo + span as u8
+        })
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Reading {
+    /// Averaged.
+    pub mv: u16,
+    pub level: u8,
+    pub power: PowerSource,
+    /// `None` while charging, or until the level dropped for a while.
+    pub minutes_left: Option<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gauge {
+    samples: [u16; WINDOW],
+    len: usize,
+    next: usize,
+    power: PowerSource,
+    /// Readings in a row that jumped up, they're kept out of the average meanwhile.
+    edge: u8,
+    /// Average and time the trend is taken from.
+    trend_from: Option<(Instant, u16)>,
+    /// Discharge in hundredths of a percent per hour, smoothed.
+    drain: Option<u32>,
+}
+
+impl Gauge {
+    pub fn new() -> Self {
+        Self {
+            samples: [0; WINDOW],
+            len: 0,
+            next: 0,
+            power: PowerSource::Battery,
+            edge: 0,
+            trend_from: None,
+            drain: None,
+        }
+    }
+
+    pub fn update(&mut self, mv: u16, now: Instant) -> Reading {
+        if let Some(average) = self.average() {
+            if mv >= average + EDGE_MV {
+                self.edge += 1;
+                if self.edge < EDGE_READINGS {
+                    return self.reading(average);
+                }
+                self.switch(PowerSource::Charging);
+            } else if mv + EDGE_MV <= average && self.power != PowerSource::Battery {
+                self.switch(PowerSource::Battery);
+            }
+        }
+        self.edge = 0;
+
+        self.samples[self.next] = mv;
+        self.next = (self.next + 1) % WINDOW;
+        self.len = (self.len + 1).min(WINDOW);
+        let average = self.average().unwrap_or(mv);
+
+        match self.trend_from {
+            None => self.trend_from = Some((now, average)),
+            Some((since, from)) if now.saturating_duration_since(since) >= TREND_WINDOW => {
+                self.trend(now.saturating_duration_since(since), from, average);
+                self.trend_from = Some((now, average));
+            }
+            Some(_) => {}
+        }
+        self.reading(average)
+    }
+
+    pub fn power(&self) -> PowerSource {
+        self.power
+    }
+
+    fn reading(&self, average: u16) -> Reading {
+        let level = level(average);
+        let minutes_left = match (self.power, self.drain) {
+            (PowerSource::Battery, Some(drain)) if drain > 0 => {
+                Some((level as u32 * 100 * 60 / drain).min(u16::MAX as u32) as u16)
+            }
+            _ => None,
+        };
+        Reading {
+            mv: average,
+            level,
+            power: self.power,
+            minutes_left,
+        }
+    }
+
+    fn average(&self) -> Option<u16> {
+        let samples = &self.samples[..self.len];
+        let sum = samples.iter().map(|mv| *mv as u32).sum::<u32>();
+        (!samples.is_empty()).then(|| (sum / samples.len() as u32) as u16)
+    }
+
+    /// The voltage went from `from` to `to` over `elapsed`.
+    fn trend(&mut self, elapsed: Duration, from: u16, to: u16) {
+        let rising = to >= from + RISE_MV;
+        match self.power {
+            PowerSource::Battery if rising => self.switch(PowerSource::Charging),
+            PowerSource::Battery => {
+                let dropped = level(from).saturating_sub(level(to)) as u64;
+                let drain = (dropped * 100 * 3600 / elapsed.as_secs().max(1)) as u32;
+                self.drain = Some(match self.drain {
+                    Some(previous) => (previous * 3 + drain) / 4,
+                    None => drain,
+                });
+            }
+            PowerSource::Charging if !rising && to >= FULL_MV => self.switch(PowerSource::Usb),
+            // stopped climbing short of full without a jump down, unplugged after all
+            PowerSource::Charging if to <= from => self.switch(PowerSource::Battery),
+            PowerSource::Charging | PowerSource::Usb => {}
+        }
+    }
+
+    /// The old readings were taken on the other source, they don't count anymore.
+    fn switch(&mut self, power: PowerSource) {
+        if power != self.power {
+            log::info!("battery: {:?} -> {:?}", self.power, power);
+            self.power = power;
+        }
+        self.len = 0;
+        self.next = 0;
+        self.edge = 0;
+        self.trend_from = None;
+        self.drain = None;
+    }
+}
+
+impl Default for Gauge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `mv(t)` every 5 s from `from` to before `to` seconds, the last reading.
+    fn feed(gauge: &mut Gauge, from: u64, to: u64, mv: impl Fn(u64) -> u16) -> Reading {
+        let mut reading = Reading::default();
+        for t in (from..to).step_by(5) {
+            reading = gauge.update(mv(t), Instant::from_secs(t));
+        }
+        reading
+    }
+
+    #[test]
+    fn level_ends() {
+        assert_eq!(level(4200), 100);
+        assert_eq!(level(4500), 100);
+        assert_eq!(level(3300), 0);
+        assert_eq!(level(3000), 0);
+        assert_eq!(level(0), 0);
+    }
+
+    #[test]
+    fn level_between_points() {
+        assert_eq!(level(3840), 50);
+        assert_eq!(level(3845), 52);
+        assert_eq!(level(3849), 54);
+        assert_eq!(level(4175), 97);
+        assert_eq!(level(3455), 2);
+    }
+
+    #[test]
+    fn level_falls_with_the_voltage() {
+        let mut last = 100;
+        for mv in (3000..=4300).rev() {
+            let level = level(mv);
+            assert!(level <= last, "{mv} mV");
+            last = level;
+        }
+    }
+
+    #[test]
+    fn averages() {
+        let mut gauge = Gauge::new();
+        gauge.update(3800, Instant::from_secs(0));
+        let reading = gauge.update(3840, Instant::from_secs(5));
+        assert_eq!(reading.mv, 3820);
+        assert_eq!(reading.power, PowerSource::Battery);
+    }
+
+    #[test]
+    fn plug_in_takes_a_few_readings() {
+        let mut gauge = Gauge::new();
+        feed(&mut gauge, 0, 40, |_| 3700);
+        for t in [40, 45] {
+            let reading = gauge.update(3850, Instant::from_secs(t));
+            assert_eq!(reading.power, PowerSource::Battery);
+            // kept out of the average until it's sure
+            assert_eq!(reading.mv, 3700);
+        }
+        let reading = gauge.update(3850, Instant::from_secs(50));
+        assert_eq!(reading.power, PowerSource::Charging);
+        assert_eq!(reading.mv, 3850);
+        assert_eq!(reading.minutes_left, None);
+    }
+
+    #[test]
+    fn spike_isnt_a_charger() {
+        let mut gauge = Gauge::new();
+        feed(&mut gauge, 0, 40, |_| 3700);
+        gauge.update(3850, Instant::from_secs(40));
+        gauge.update(3850, Instant::from_secs(45));
+        let reading = gauge.update(3700, Instant::from_secs(50));
+        assert_eq!(reading.power, PowerSource::Battery);
+        assert_eq!(reading.mv, 3700);
+        // the count starts over
+        let reading = gauge.update(3850, Instant::from_secs(55));
+        assert_eq!(reading.power, PowerSource::Battery);
+    }
+
+    #[test]
+    fn pull_is_right_away() {
+        let mut gauge = Gauge::new();
+        feed(&mut gauge, 0, 40, |_| 3700);
+        feed(&mut gauge, 40, 100, |_| 3900);
+        assert_eq!(gauge.power(), PowerSource::Charging);
+        let reading = gauge.update(3750, Instant::from_secs(100));
+        assert_eq!(reading.power, PowerSource::Battery);
+        assert_eq!(reading.mv, 3750);
+    }
+
+    #[test]
+    fn rising_trend_is_charging_then_usb() {
+        let mut gauge = Gauge::new();
+        // 0.1 mV/s, never a jump
+        let reading = feed(&mut gauge, 0, 300, |t| 3900 + t as u16 / 10);
+        assert_eq!(reading.power, PowerSource::Battery);
+        let reading = gauge.update(3930, Instant::from_secs(300));
+        assert_eq!(reading.power, PowerSource::Charging);
+
+        // climbs to full and stays there
+        let reading = feed(&mut gauge, 305, 1500, |t| {
+            (3930 + (t - 300) as u16 / 2).min(4180)
+        });
+        assert_eq!(reading.power, PowerSource::Usb);
+        assert_eq!(reading.minutes_left, None);
+    }
+
+    #[test]
+    fn charging_stalled_below_full_is_battery() {
+        let mut gauge = Gauge::new();
+        feed(&mut gauge, 0, 40, |_| 3700);
+        feed(&mut gauge, 40, 700, |_| 3850);
+        assert_eq!(gauge.power(), PowerSource::Battery);
+    }
+
+    #[test]
+    fn minutes_left() {
+        let mut gauge = Gauge::new();
+        // 50 % down to 40 % in five minutes
+        let reading = feed(&mut gauge, 0, 300, |t| 3840 - (t * 40 / 300) as u16);
+        assert_eq!(reading.minutes_left, None);
+        let reading = gauge.update(3800, Instant::from_secs(300));
+        assert_eq!(reading.level, 40);
+        assert_eq!(reading.minutes_left, Some(20));
+    }
+}
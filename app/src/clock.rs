@@ -0,0 +1,200 @@
+//! Wall-clock time on top of `embassy_time::Instant`.
+//!
+//! Nothing knows the time at boot. Once something (SNTP, the host clock in the simulator)
+//! calls [`set_unix_time`], `Instant`s can be turned into UTC and local time.
+
+use core::{cell::Cell, fmt};
+
+use critical_section::Mutex;
+use embassy_time::Instant;
+
+/// Unix time in ms at `Instant` zero, `None` until synced.
+static BOOT_UNIX_MS: Mutex<Cell<Option<u64>>> = Mutex::new(Cell::new(None));
+static UTC_OFFSET_MINUTES: Mutex<Cell<i32>> = Mutex::new(Cell::new(0));
+
+pub fn set_unix_time(unix_ms: u64) {
+    let boot = unix_ms.saturating_sub(Instant::now().as_millis());
+    critical_section::with(|cs| BOOT_UNIX_MS.borrow(cs).set(Some(boot)));
+}
+
+pub fn is_synced() -> bool {
+    critical_section::with(|cs| BOOT_UNIX_MS.borrow(cs).get().is_some())
+}
+
+pub fn set_utc_offset(minutes: i32) {
+    critical_section::with(|cs| UTC_OFFSET_MINUTES.borrow(cs).set(minutes));
+}
+
+pub fn utc_offset() -> i32 {
+    critical_section::with(|cs| UTC_OFFSET_MINUTES.borrow(cs).get())
+}
+
+/// Parses `+02:00`, `-0530`, `+3` or `Z`.
+pub fn parse_utc_offset(s: &str) -> Option<i32> {
+    let s = s.trim();
+    if s == "Z" || s == "UTC" {
+        return Some(0);
+    }
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => (1, s),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at_checked(2)?,
+        None => (rest, "0"),
+    };
+    // `parse` would take another sign, as in `+-3`
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(hours) || !digits(minutes) {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Unix time in ms of an `Instant`, if the clock is synced.
+pub fn unix_ms_at(instant: Instant) -> Option<u64> {
+    critical_section::with(|cs| BOOT_UNIX_MS.borrow(cs).get())
+        .map(|boot| boot + instant.as_millis())
+}
+
+pub fn now_utc() -> Option<DateTime> {
+    unix_ms_at(Instant::now()).map(|ms| DateTime::from_unix((ms / 1000) as i64, 0))
+}
+
+pub fn now_local() -> Option<DateTime> {
+    unix_ms_at(Instant::now()).map(|ms| DateTime::from_unix((ms / 1000) as i64, utc_offset()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// Minutes east of UTC.
+    pub offset: i32,
+}
+
+impl DateTime {
+    /// https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn from_unix(secs: i64, offset_minutes: i32) -> Self {
+        let secs = secs + offset_minutes as i64 * 60;
+        let days = secs.div_euclid(86_400);
+        let time = secs.rem_euclid(86_400);
+
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year: year as i32,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+            offset: offset_minutes,
+        }
+    }
+}
+
+/// RFC 3339, e.g. `2025-10-18T14:03:07+02:00`.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        if self.offset == 0 {
+            write!(f, "Z")
+        } else {
+            let sign = if self.offset < 0 { '-' } else { '+' };
+            let offset = self.offset.abs();
+            write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(secs: i64, offset: i32) -> (i32, u8, u8, u8, u8, u8) {
+        let t = DateTime::from_unix(secs, offset);
+        (t.year, t.month, t.day, t.hour, t.minute, t.second)
+    }
+
+    #[test]
+    fn epoch() {
+        assert_eq!(date(0, 0), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(date(-1, 0), (1969, 12, 31, 23, 59, 59));
+        assert_eq!(
+            format!("{}", DateTime::from_unix(0, 0)),
+            "1970-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn leap_day() {
+        // 2024-02-29T12:00:00Z
+        assert_eq!(date(1_709_208_000, 0), (2024, 2, 29, 12, 0, 0));
+        assert_eq!(date(1_709_208_000 + 86_400, 0), (2024, 3, 1, 12, 0, 0));
+    }
+
+    #[test]
+    fn no_leap_day_in_2100() {
+        // 2100-02-28T00:00:00Z, the next day is March
+        assert_eq!(date(4_107_456_000, 0), (2100, 2, 28, 0, 0, 0));
+        assert_eq!(date(4_107_456_000 + 86_400, 0), (2100, 3, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn offset_moves_the_date() {
+        assert_eq!(date(0, -90), (1969, 12, 31, 22, 30, 0));
+        assert_eq!(
+            format!("{}", DateTime::from_unix(0, 330)),
+            "1970-01-01T05:30:00+05:30"
+        );
+        assert_eq!(
+            format!("{}", DateTime::from_unix(0, -60)),
+            "1969-12-31T23:00:00-01:00"
+        );
+    }
+
+    #[test]
+    fn utc_offsets() {
+        assert_eq!(parse_utc_offset("Z"), Some(0));
+        assert_eq!(parse_utc_offset("UTC"), Some(0));
+        assert_eq!(parse_utc_offset("+02:00"), Some(120));
+        assert_eq!(parse_utc_offset("+5:30"), Some(330));
+        assert_eq!(parse_utc_offset("-0530"), Some(-330));
+        assert_eq!(parse_utc_offset("+3"), Some(180));
+        assert_eq!(parse_utc_offset(" 1 "), Some(60));
+        assert_eq!(parse_utc_offset("+14"), Some(840));
+    }
+
+    #[test]
+    fn bad_utc_offsets() {
+        for offset in [
+            "", "+", "+-3", "-+3", "++3", "+15", "-24", "+01:60", "+1:", "+:30", "+ab", "+1.5",
+        ] {
+            assert_eq!(parse_utc_offset(offset), None, "{:?}", offset);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! Labelled values picked out of a JSON document fetched over HTTP, for the dash tab.
+//!
+//! Only what a LAN service needs: plain `http://`, HTTP/1.0 so the body is never chunked,
+//! and fields chosen with [JSON pointers](https://www.rfc-editor.org/rfc/rfc6901) like
+//! `/attributes/temperature`. The platform moves the bytes.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use core::fmt;
+
+use serde_json::Value;
+
+/// Responses are cut off here.
+pub const MAX_RESPONSE_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Url,
+    /// Not an HTTP response, or cut off before the body.
+    Response,
+    Status(u16),
+    Json,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Url => write!(f, "bad url"),
+            Error::Response => write!(f, "bad response"),
+            Error::Status(status) => write!(f, "HTTP {}", status),
+            Error::Json => write!(f, "bad JSON"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub label: String,
+    pub pointer: String,
+}
+
+/// `label=/pointer,...`, without a label the last path segment is used.
+pub fn parse_fields(spec: &str) -> Vec<Field> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| match field.split_once('=') {
+            Some((label, pointer)) => Field {
+                label: label.trim().into(),
+                pointer: pointer.trim().into(),
+            },
+            None => Field {
+                label: field.rsplit('/').next().unwrap_or(field).into(),
+                pointer: field.into(),
+            },
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Url<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    /// With the query, `/` at least.
+    pub path: &'a str,
+}
+
+impl<'a> Url<'a> {
+    /// `http://host[:port][/path]`
+    pub fn parse(url: &'a str) -> Result<Self, Error> {
+        let rest = url.strip_prefix("http://").ok_or(Error::Url)?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.split_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| Error::Url)?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(Error::Url);
+        }
+        Ok(Self { host, port, path })
+    }
+}
+
+/// A GET for `url`, with a bearer token for e.g. the Home Assistant REST API.
+pub fn request(url: &Url, token: Option<&str>) -> String {
+    let mut request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n",
+        url.path, url.host
+    );
+    if let Some(token) = token {
+        request.push_str(&format!("Authorization: Bearer {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+/// The body of a 2xx response.
+pub fn parse_response(response: &[u8]) -> Result<&[u8], Error> {
+    let end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or(Error::Response)?;
+    let head = core::str::from_utf8(&response[..end]).map_err(|_| Error::Response)?;
+    let status = head
+        .lines()
+        .next()
+        .filter(|line| line.starts_with("HTTP/"))
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or(Error::Response)?;
+    if !(200..300).contains(&status) {
+        return Err(Error::Status(status));
+    }
+    Ok(&response[end + 4..])
+}
+
+/// One `(label, value)` row per field, `-` for the ones that aren't there.
+pub fn extract(body: &[u8], fields: &[Field]) -> Result<Vec<(String, String)>, Error> {
+    let document = serde_json::from_slice::<Value>(body).map_err(|_| Error::Json)?;
+    Ok(fields
+        .iter()
+        .map(|field| {
+            let value = match document.pointer(&field.pointer) {
+                Some(Value::String(s)) => s.clone(),
+                None | Some(Value::Null) => "-".into(),
+                Some(value) => value.to_string(),
+            };
+            (field.label.clone(), value)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(label: &str, pointer: &str) -> Field {
+        Field {
+            label: label.into(),
+            pointer: pointer.into(),
+        }
+    }
+
+    #[test]
+    fn url_with_port_and_path() {
+        assert_eq!(
+            Url::parse("http://ha.local:8123/api/states/sun.sun?x=1"),
+            Ok(Url {
+                host: "ha.local",
+                port: 8123,
+                path: "/api/states/sun.sun?x=1",
+            })
+        );
+    }
+
+    #[test]
+    fn url_defaults() {
+        assert_eq!(
+            Url::parse("http://10.0.0.2"),
+            Ok(Url {
+                host: "10.0.0.2",
+                port: 80,
+                path: "/",
+            })
+        );
+    }
+
+    #[test]
+    fn bad_urls() {
+        for url in [
+            "https://ha.local/",
+            "ha.local/",
+            "http://",
+            "http://:80/",
+            "http://ha.local:port/",
+            "http://ha.local:70000/",
+        ] {
+            assert_eq!(Url::parse(url), Err(Error::Url), "{}", url);
+        }
+    }
+
+    #[test]
+    fn request_with_token() {
+        let url = Url::parse("http://ha.local:8123/api").unwrap();
+        assert_eq!(
+            request(&url, Some("abc")),
+            "GET /api HTTP/1.0\r\nHost: ha.local\r\nAccept: application/json\r\n\
+             Authorization: Bearer abc\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn body_of_a_2xx() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"a\":1}";
+        assert_eq!(parse_response(response), Ok(&b"{\"a\":1}"[..]));
+        assert_eq!(
+            parse_response(b"HTTP/1.0 204 No Content\r\n\r\n"),
+            Ok(&b""[..])
+        );
+    }
+
+    #[test]
+    fn status_other_than_2xx() {
+        assert_eq!(
+            parse_response(b"HTTP/1.0 401 Unauthorized\r\n\r\nno"),
+            Err(Error::Status(401))
+        );
+        assert_eq!(
+            parse_response(b"HTTP/1.0 301 Moved\r\nLocation: /x\r\n\r\n"),
+            Err(Error::Status(301))
+        );
+    }
+
+    #[test]
+    fn not_a_response() {
+        // cut off in the headers
+        assert_eq!(
+            parse_response(b"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n"),
+            Err(Error::Response)
+        );
+        assert_eq!(
+            parse_response(b"SSH-2.0-OpenSSH\r\n\r\n"),
+            Err(Error::Response)
+        );
+        assert_eq!(parse_response(b"HTTP/1.0 OK\r\n\r\n"), Err(Error::Response));
+    }
+
+    #[test]
+    fn body_cut_off_at_max_size() {
+        let mut response = b"HTTP/1.0 200 OK\r\n\r\n{\"a\":\"".to_vec();
+        response.resize(MAX_RESPONSE_SIZE + 100, b'x');
+        response.extend_from_slice(b"\"}");
+        response.truncate(MAX_RESPONSE_SIZE);
+
+        let body = parse_response(&response).unwrap();
+        assert_eq!(body.len(), MAX_RESPONSE_SIZE - 19);
+        assert_eq!(extract(body, &[field("a", "/a")]), Err(Error::Json));
+    }
+
+    #[test]
+    fn field_labels() {
+        assert_eq!(
+            parse_fields(" temp = /state , /attributes/unit,,"),
+            [field("temp", "/state"), field("unit", "/attributes/unit")]
+        );
+        assert_eq!(parse_fields(""), []);
+    }
+
+    #[test]
+    fn extracted_values() {
+        let body = br#"{"state":"21.5","attributes":{"on":true,"n":3,"gone":null,"list":[1,2]}}"#;
+        let fields = parse_fields(
+            "/state,on=/attributes/on,/attributes/n,/attributes/gone,/nope,/attributes/list",
+        );
+        assert_eq!(
+            extract(body, &fields).unwrap(),
+            [
+                ("state".into(), "21.5".into()),
+                ("on".into(), "true".into()),
+                ("n".into(), "3".into()),
+                ("gone".into(), "-".into()),
+                ("nope".into(), "-".into()),
+                ("list".into(), "[1,2]".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_json() {
+        assert_eq!(extract(b"<html>", &[field("a", "/a")]), Err(Error::Json));
+    }
+
+    /// The whole round trip, the way the simulator does it.
+    #[cfg(feature = "std")]
+    #[test]
+    fn against_a_server() {
+        use std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+            thread,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 256];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                assert!(n > 0);
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\n\r\n{\"state\":\"on\",\"attributes\":{\"w\":12.5}}")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let url = format!("http://127.0.0.1:{}/api/states/switch.lamp", port);
+        let url = Url::parse(&url).unwrap();
+        let mut stream = TcpStream::connect((url.host, url.port)).unwrap();
+        stream
+            .write_all(request(&url, Some("secret")).as_bytes())
+            .unwrap();
+        let mut response = Vec::new();
+        stream
+            .take(MAX_RESPONSE_SIZE as u64)
+            .read_to_end(&mut response)
+            .unwrap();
+        let body = parse_response(&response).unwrap();
+        let rows = extract(body, &parse_fields("/state,watts=/attributes/w")).unwrap();
+        assert_eq!(
+            rows,
+            [
+                ("state".into(), "on".into()),
+                ("watts".into(), "12.5".into())
+            ]
+        );
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /api/states/switch.lamp HTTP/1.0\r\n"));
+        assert!(request.contains("\r\nAuthorization: Bearer secret\r\n"));
+    }
+}
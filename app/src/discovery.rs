@@ -0,0 +1,298 @@
+//! Sticks finding each other on a LAN: each one multicasts an [`Announce`] every few seconds
+//! and keeps the ones it hears in a peer table.
+//!
+//! The table is global so anything that needs a target device can pick one with [`find`],
+//! the platform feeds it with [`record`] and [`expire`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::{cell::RefCell, fmt, net::Ipv4Addr};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use critical_section::Mutex;
+use embassy_time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{Event, StickHat},
+    role::Role,
+};
+
+/// Administratively scoped, stays on the LAN.
+pub const GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 90, 1);
+pub const PORT: u16 = 9001;
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(5);
+/// Peers not heard from for three announcements are dropped.
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(15);
+/// The one heard from longest ago makes room for a new one.
+pub const MAX_PEERS: usize = 16;
+pub const MAX_ANNOUNCE_SIZE: usize = 128;
+
+/// Tells announcements apart from whatever else is on the group, the last byte is the version.
+const MAGIC: [u8; 4] = *b"stk\x01";
+
+static PEERS: Mutex<RefCell<PeerTable>> = Mutex::new(RefCell::new(PeerTable::new()));
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Sends TV remote codes.
+    pub ir: bool,
+    /// Wears the 8Servos hat.
+    pub servos: bool,
+    /// Wears the MiniJoyC hat.
+    pub joystick: bool,
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.ir, "ir"),
+            (self.servos, "srv"),
+            (self.joystick, "joy"),
+        ]
+        .iter()
+        .filter(|(has, _)| *has)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Announce {
+    pub name: String,
+    pub role: Role,
+    /// Percent.
+    pub battery: u8,
+    pub capabilities: Capabilities,
+}
+
+impl Announce {
+    pub fn new(name: &str, role: Role) -> Self {
+        Self {
+            name: name.into(),
+            role,
+            battery: 0,
+            capabilities: Capabilities {
+                ir: role == Role::Remote,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Picks up the battery level and hats from the event bus.
+    pub fn update(&mut self, event: &Event) {
+        match event {
+            Event::StatsUpdated(stats) => self.battery = stats.battery_level,
+            Event::InitHat(StickHat::MiniJoyC) => self.capabilities.joystick = true,
+            Event::InitHat(StickHat::Hat8Servos) => self.capabilities.servos = true,
+            _ => {}
+        }
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Option<usize> {
+        buf.get_mut(..MAGIC.len())?.copy_from_slice(&MAGIC);
+        let len = postcard::to_slice(self, &mut buf[MAGIC.len()..])
+            .ok()?
+            .len();
+        Some(MAGIC.len() + len)
+    }
+
+    pub fn decode(datagram: &[u8]) -> Option<Self> {
+        let payload = datagram.strip_prefix(&MAGIC)?;
+        postcard::from_bytes(payload).ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peer {
+    pub ip: Ipv4Addr,
+    pub announce: Announce,
+    pub last_seen: Instant,
+}
+
+/// Keyed by name, a stick that changes address is still the same peer.
+#[derive(Debug, Default)]
+pub struct PeerTable {
+    peers: Vec<Peer>,
+}
+
+impl PeerTable {
+    pub const fn new() -> Self {
+        Self { peers: Vec::new() }
+    }
+
+    /// `true` if the peer is new or changed.
+    pub fn update(&mut self, ip: Ipv4Addr, announce: Announce, now: Instant) -> bool {
+        if let Some(peer) = self
+            .peers
+            .iter_mut()
+            .find(|peer| peer.announce.name == announce.name)
+        {
+            let changed = peer.ip != ip || peer.announce != announce;
+            peer.ip = ip;
+            peer.announce = announce;
+            peer.last_seen = now;
+            return changed;
+        }
+
+        if self.peers.len() >= MAX_PEERS
+            && let Some(oldest) = self
+                .peers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, peer)| peer.last_seen)
+                .map(|(i, _)| i)
+        {
+            self.peers.remove(oldest);
+        }
+        self.peers.push(Peer {
+            ip,
+            announce,
+            last_seen: now,
+        });
+        self.peers
+            .sort_by(|a, b| a.announce.name.cmp(&b.announce.name));
+        true
+    }
+
+    /// Drops the peers that went quiet, `true` if there were any.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let len = self.peers.len();
+        self.peers
+            .retain(|peer| now.saturating_duration_since(peer.last_seen) < PEER_TIMEOUT);
+        self.peers.len() != len
+    }
+
+    /// By name.
+    pub fn peers(&self) -> &[Peer] {
+        &self.peers
+    }
+}
+
+/// Adds or refreshes a peer that was heard from, `true` if the table changed.
+pub fn record(ip: Ipv4Addr, announce: Announce) -> bool {
+    critical_section::with(|cs| {
+        PEERS
+            .borrow_ref_mut(cs)
+            .update(ip, announce, Instant::now())
+    })
+}
+
+/// Drops the peers that went quiet, `true` if the table changed.
+pub fn expire() -> bool {
+    critical_section::with(|cs| PEERS.borrow_ref_mut(cs).expire(Instant::now()))
+}
+
+/// The peers heard from lately, by name.
+pub fn peers() -> Vec<Peer> {
+    critical_section::with(|cs| PEERS.borrow_ref(cs).peers().to_vec())
+}
+
+/// The first peer by name that matches, e.g. a vehicle to drive:
+/// `find(|peer| peer.announce.capabilities.servos)`.
+pub fn find(matches: impl Fn(&Peer) -> bool) -> Option<Peer> {
+    critical_section::with(|cs| {
+        PEERS
+            .borrow_ref(cs)
+            .peers()
+            .iter()
+            .find(|peer| matches(peer))
+            .cloned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> Instant {
+        Instant::from_secs(secs)
+    }
+
+    fn ip(last: u8) -> Ipv4Addr {
+        Ipv4Addr::new(192, 168, 1, last)
+    }
+
+    fn announce(name: &str) -> Announce {
+        Announce::new(name, Role::Remote)
+    }
+
+    fn names(table: &PeerTable) -> Vec<&str> {
+        table
+            .peers()
+            .iter()
+            .map(|peer| peer.announce.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sorted_by_name() {
+        let mut table = PeerTable::new();
+        assert!(table.update(ip(3), announce("c"), at(0)));
+        assert!(table.update(ip(1), announce("a"), at(0)));
+        assert!(table.update(ip(2), announce("b"), at(0)));
+        assert_eq!(names(&table), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn updated_in_place() {
+        let mut table = PeerTable::new();
+        table.update(ip(1), announce("a"), at(0));
+        // heard again, nothing new
+        assert!(!table.update(ip(1), announce("a"), at(5)));
+        assert_eq!(table.peers()[0].last_seen, at(5));
+
+        // new address, same stick
+        assert!(table.update(ip(9), announce("a"), at(6)));
+        assert_eq!(table.peers().len(), 1);
+        assert_eq!(table.peers()[0].ip, ip(9));
+
+        let mut charged = announce("a");
+        charged.battery = 80;
+        assert!(table.update(ip(9), charged, at(7)));
+        assert_eq!(table.peers()[0].announce.battery, 80);
+    }
+
+    #[test]
+    fn quiet_peers_expire() {
+        let mut table = PeerTable::new();
+        table.update(ip(1), announce("a"), at(0));
+        table.update(ip(2), announce("b"), at(10));
+        assert!(!table.expire(at(0) + PEER_TIMEOUT - Duration::from_secs(1)));
+        assert!(table.expire(at(0) + PEER_TIMEOUT));
+        assert_eq!(names(&table), ["b"]);
+        assert!(!table.expire(at(0) + PEER_TIMEOUT));
+        assert!(table.expire(at(10) + PEER_TIMEOUT));
+        assert!(table.peers().is_empty());
+    }
+
+    #[test]
+    fn full_table_drops_the_one_heard_longest_ago() {
+        let mut table = PeerTable::new();
+        for i in 0..MAX_PEERS {
+            table.update(ip(i as u8), announce(&format!("p{:02}", i)), at(i as u64));
+        }
+        // p00 is the oldest but was heard again
+        table.update(ip(0), announce("p00"), at(100));
+
+        assert!(table.update(ip(200), announce("new"), at(101)));
+        let names = names(&table);
+        assert_eq!(names.len(), MAX_PEERS);
+        assert!(names.contains(&"p00"));
+        assert!(!names.contains(&"p01"));
+        assert!(names.contains(&"new"));
+    }
+
+    #[test]
+    fn announce_round_trip() {
+        let mut announce = Announce::new("stick-abcdef", Role::Vehicle);
+        announce.update(&Event::InitHat(StickHat::Hat8Servos));
+        let mut buf = [0u8; MAX_ANNOUNCE_SIZE];
+        let len = announce.encode(&mut buf).unwrap();
+        assert_eq!(Announce::decode(&buf[..len]), Some(announce));
+        // something else on the group
+        assert_eq!(Announce::decode(b"M-SEARCH * HTTP/1.1"), None);
+    }
+}
@@ -1,9 +1,20 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::net::Ipv4Addr;
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
     pubsub::{PubSubChannel, Publisher, Subscriber},
 };
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    auth::PairingPin, battery::PowerSource, idle::IdleState, input::Gesture, overlay::Confirm,
+    pager::Page,
+};
+
 #[derive(Debug, Clone)]
 pub enum Event {
     InitHat(StickHat),
@@ -11,26 +22,187 @@ pub enum Event {
     StatsUpdated(Stats),
     ButtonDown(Button),
     ButtonUp(Button),
+    /// Made out of the button events by the app, see [`crate::input`]. Screens get these
+    /// instead of `ButtonDown`/`ButtonUp`, they never go out on the bus.
+    Gesture(Gesture),
     Remote(Remote),
     JoyC(JoyC),
     LogAdded,
     Controller(Controller),
     Vehicle(Vehicle),
+    /// Switch to the tab with this title.
+    SelectTab(String),
+    /// Leases handed out by the AP's DHCP server, republished periodically.
+    DhcpLeases(Vec<DhcpLease>),
+    /// Drop the DHCP lease of this MAC.
+    KickClient([u8; 6]),
+    NetStatus(NetStatus),
+    /// Ask the platform for a Wi-Fi scan, answered with [`Event::ScanResults`].
+    Scan,
+    /// Access points found by the last scan, empty if it failed.
+    ScanResults(Vec<AccessPoint>),
+    /// Sniffed frames of the last second, see [`crate::monitor`].
+    PacketStats(PacketStats),
+    /// A message for the inbox.
+    Page(Page),
+    /// The newest page was read off its overlay.
+    PageRead,
+    /// Broadcast a message to the sticks around over ESP-NOW.
+    SendPage(Page),
+    /// Latest poll of the dashboard endpoint.
+    Dashboard(Dashboard),
+    /// A stick showed up, changed or went quiet, see [`crate::discovery::peers`].
+    PeersChanged,
+    /// A host is pairing and the PIN goes on screen, `None` once it's done.
+    PairingPin(Option<PairingPin>),
+    /// End the session of this paired host, see [`crate::auth::revoke`].
+    RevokeClient(String),
+    /// Backlight brightness in percent, see [`crate::backlight`].
+    SetBrightness(u8),
+    /// The screen dimmed, went off or came back, see [`crate::idle`].
+    Idle(IdleState),
+    /// A few words over the screen for a moment, see [`crate::overlay`].
+    Toast(String),
+    /// Ask before doing something, the action goes out on a yes.
+    Confirm(Confirm),
+    /// The stick is about to cut its own power, last chance to save anything.
+    PowerOff,
+    /// The stick is going to deep sleep until a button wakes it, see [`crate::sleep`].
+    DeepSleep,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum StickHat {
     MiniJoyC,
+    Hat8Servos,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Stats {
     pub battery_level: u8,
+    /// Averaged, see [`crate::battery::Gauge`].
+    pub battery_mv: u16,
+    pub power: PowerSource,
+    /// Until the battery is empty, `None` while that's not known.
+    pub minutes_left: Option<u16>,
     pub heap_used: usize,
     pub heap_free: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetStatus {
+    pub mode: NetMode,
+    /// Joined network, or the AP's own SSID.
+    pub ssid: Option<String>,
+    pub ip: Option<Ipv4Addr>,
+    pub gateway: Option<Ipv4Addr>,
+    /// Signal of the joined network in dBm.
+    pub rssi: Option<i8>,
+    /// Seconds since the STA (re)connected.
+    pub connected_for: Option<u32>,
+    pub reconnects: u32,
+    pub udp_rx: u32,
+    pub udp_tx: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::Display)]
+pub enum NetMode {
+    #[default]
+    #[strum(to_string = "off")]
+    Off,
+    #[strum(to_string = "ap")]
+    Ap,
+    #[strum(to_string = "sta")]
+    Sta,
+    #[strum(to_string = "ap+sta")]
+    ApSta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DhcpLease {
+    pub mac: [u8; 6],
+    pub ip: Ipv4Addr,
+    /// Seconds until the lease expires, 0 once it has.
+    pub expires_in: u32,
+    /// Seconds since the client last requested or renewed the lease.
+    pub last_seen: u32,
+}
+
+impl DhcpLease {
+    pub fn is_stale(&self) -> bool {
+        self.expires_in == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPoint {
+    /// Empty for hidden networks.
+    pub ssid: String,
+    pub bssid: [u8; 6],
+    pub channel: u8,
+    /// dBm
+    pub rssi: i8,
+    pub auth: AuthMode,
+}
+
+impl AccessPoint {
+    /// How much of `channel` this AP's 20 MHz band covers, in quarters: 4 on its own
+    /// channel, 3 next to it, 0 from four channels away (2.4 GHz channels are 5 MHz apart).
+    pub fn overlap(&self, channel: u8) -> u8 {
+        4u8.saturating_sub(self.channel.abs_diff(channel))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::Display)]
+pub enum AuthMode {
+    #[default]
+    #[strum(to_string = "open")]
+    Open,
+    #[strum(to_string = "wep")]
+    Wep,
+    #[strum(to_string = "wpa")]
+    Wpa,
+    #[strum(to_string = "wpa2")]
+    Wpa2,
+    #[strum(to_string = "wpa/2")]
+    WpaWpa2,
+    #[strum(to_string = "wpa2/3")]
+    Wpa2Wpa3,
+    #[strum(to_string = "wpa3")]
+    Wpa3,
+    #[strum(to_string = "eap")]
+    Enterprise,
+    #[strum(to_string = "?")]
+    Other,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PacketStats {
+    pub frames: u32,
+    pub esp_now: u32,
+    /// Frames per channel, channel 1 first.
+    pub channels: [u32; crate::monitor::CHANNELS],
+    /// Busiest sources since the monitor started, most frames first.
+    pub talkers: Vec<Talker>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Talker {
+    pub mac: [u8; 6],
+    pub frames: u32,
+    pub esp_now: u32,
+    /// Of the latest frame, dBm.
+    pub rssi: i8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dashboard {
+    /// `(label, value)`, empty if the poll failed.
+    pub rows: Vec<(String, String)>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Button {
     A,
     B,
@@ -63,7 +235,20 @@ pub enum JoycDirection {
     Center,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, strum::EnumIter, strum::EnumCount, strum::FromRepr)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::EnumCount,
+    strum::FromRepr,
+    strum::EnumString,
+    strum::IntoStaticStr,
+)]
+#[strum(serialize_all = "kebab-case")]
 pub enum Remote {
     OnOff,
     Home,
@@ -78,8 +263,32 @@ pub enum Remote {
     VolumeDown,
 }
 
-pub type Channel = PubSubChannel<CriticalSectionRawMutex, Event, 4, 4, 4>;
-pub type Sender = Publisher<'static, CriticalSectionRawMutex, Event, 4, 4, 4>;
-pub type Receiver = Subscriber<'static, CriticalSectionRawMutex, Event, 4, 4, 4>;
+/// Events queued before a publisher waits for the slowest subscriber.
+pub const CAPACITY: usize = 4;
+/// Every task holds its own publisher, the stick with all features on takes 15.
+pub const MAX_PUBLISHERS: usize = 16;
+/// The stick with all features on takes 9.
+pub const MAX_SUBSCRIBERS: usize = 10;
+
+pub type Channel =
+    PubSubChannel<CriticalSectionRawMutex, Event, CAPACITY, MAX_SUBSCRIBERS, MAX_PUBLISHERS>;
+pub type Sender =
+    Publisher<'static, CriticalSectionRawMutex, Event, CAPACITY, MAX_SUBSCRIBERS, MAX_PUBLISHERS>;
+pub type Receiver =
+    Subscriber<'static, CriticalSectionRawMutex, Event, CAPACITY, MAX_SUBSCRIBERS, MAX_PUBLISHERS>;
 
 pub static EVENTS: Channel = Channel::new();
+
+/// A publisher on [`EVENTS`], panics if they're all taken.
+pub fn sender() -> Sender {
+    EVENTS
+        .publisher()
+        .expect("out of event publishers, raise MAX_PUBLISHERS")
+}
+
+/// A subscriber on [`EVENTS`], panics if they're all taken.
+pub fn receiver() -> Receiver {
+    EVENTS
+        .subscriber()
+        .expect("out of event subscribers, raise MAX_SUBSCRIBERS")
+}
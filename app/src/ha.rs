@@ -0,0 +1,70 @@
+//! Home Assistant MQTT discovery configs for the topics in [`crate::mqtt`].
+//!
+//! https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use crate::{
+    events::Button,
+    mqtt::{ONLINE, STATUS_TOPIC, button_topic},
+};
+
+pub const DISCOVERY_PREFIX: &str = "homeassistant";
+/// Home Assistant publishes `online` here when it (re)starts, configs should be sent again.
+pub const HA_STATUS_TOPIC: &str = "homeassistant/status";
+
+/// Retained `(topic, payload)` pairs announcing the stick's entities.
+///
+/// `node_id` is the stick hostname, `base` its MQTT base topic.
+pub fn discovery_configs(node_id: &str, base: &str) -> Vec<(String, String)> {
+    let device = format!(
+        r#"{{"identifiers":["{id}"],"name":"{id}","manufacturer":"M5Stack","model":"M5StickC PLUS2","sw_version":"{version}"}}"#,
+        id = node_id,
+        version = env!("CARGO_PKG_VERSION"),
+    );
+    let availability =
+        format!(r#""availability_topic":"{base}/{STATUS_TOPIC}","payload_available":"{ONLINE}""#);
+
+    let mut configs = Vec::new();
+
+    configs.push((
+        format!("{DISCOVERY_PREFIX}/sensor/{node_id}/battery/config"),
+        format!(
+            r#"{{"name":"Battery","unique_id":"{node_id}_battery","state_topic":"{base}/stats","value_template":"{{{{ value_json.battery }}}}","unit_of_measurement":"%","device_class":"battery","state_class":"measurement",{availability},"device":{device}}}"#
+        ),
+    ));
+
+    for (button, name) in [(Button::A, "a"), (Button::B, "b"), (Button::C, "c")] {
+        configs.push((
+            format!("{DISCOVERY_PREFIX}/device_automation/{node_id}/button_{name}/config"),
+            format!(
+                r#"{{"automation_type":"trigger","topic":"{base}/{topic}","payload":"released","type":"button_short_press","subtype":"button_{name}","device":{device}}}"#,
+                topic = button_topic(button),
+            ),
+        ));
+    }
+
+    #[cfg(feature = "tv")]
+    {
+        use strum::IntoEnumIterator;
+
+        use crate::events::Remote;
+
+        for remote in Remote::iter() {
+            let key: &'static str = remote.into();
+            let object_id = key.replace('-', "_");
+            configs.push((
+                format!("{DISCOVERY_PREFIX}/button/{node_id}/remote_{object_id}/config"),
+                format!(
+                    r#"{{"name":"TV {name}","unique_id":"{node_id}_remote_{object_id}","command_topic":"{base}/cmd/remote","payload_press":"{key}","icon":"mdi:remote-tv",{availability},"device":{device}}}"#,
+                    name = String::from(remote),
+                ),
+            ));
+        }
+    }
+
+    configs
+}
@@ -0,0 +1,192 @@
+//! Dims and then blanks the screen when nobody touches the stick.
+//!
+//! The [`IdleTimer`] decides, driven by the time it's given like
+//! [`crate::input::Recognizer`]. The app keeps the current [`IdleState`] here, where the
+//! platform can read it from anywhere (say while flushing a frame), and publishes
+//! [`Event::Idle`] when it changes.
+//!
+//! [`Event::Idle`]: crate::events::Event::Idle
+
+use core::cell::Cell;
+
+use critical_section::Mutex;
+use embassy_time::{Duration, Instant};
+
+pub const DIM_AFTER: Duration = Duration::from_secs(20);
+/// Counted from the last input too, not from dimming.
+pub const OFF_AFTER: Duration = Duration::from_secs(60);
+/// Backlight while dimmed, unless it's set lower already.
+pub const DIM_LEVEL: u8 = 5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum IdleState {
+    #[default]
+    Active,
+    Dimmed,
+    /// Backlight and panel off, the platform may sleep until a button goes down.
+    Off,
+}
+
+impl IdleState {
+    /// The backlight level for this state, `brightness` being what was picked.
+    pub fn backlight(self, brightness: u8) -> u8 {
+        match self {
+            IdleState::Active => brightness,
+            IdleState::Dimmed => brightness.min(DIM_LEVEL),
+            IdleState::Off => 0,
+        }
+    }
+}
+
+static STATE: Mutex<Cell<IdleState>> = Mutex::new(Cell::new(IdleState::Active));
+
+pub fn state() -> IdleState {
+    critical_section::with(|cs| STATE.borrow(cs).get())
+}
+
+pub(crate) fn set_state(state: IdleState) {
+    critical_section::with(|cs| STATE.borrow(cs).set(state));
+}
+
+/// What [`IdleTimer::input`] made of a button or a joystick move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    /// The screen was on already.
+    Used,
+    /// Brought the dimmed screen back, the input still counts.
+    Brightened,
+    /// Turned the screen back on, nothing else should come of it.
+    Woke,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdleTimer {
+    dim_after: Duration,
+    off_after: Duration,
+    last_input: Instant,
+    state: IdleState,
+}
+
+impl IdleTimer {
+    pub fn new(dim_after: Duration, off_after: Duration, now: Instant) -> Self {
+        Self {
+            dim_after,
+            off_after,
+            last_input: now,
+            state: IdleState::Active,
+        }
+    }
+
+    pub fn state(&self) -> IdleState {
+        self.state
+    }
+
+    /// Someone is using the stick, the screen comes back if it was dimmed or off.
+    pub fn input(&mut self, now: Instant) -> Input {
+        self.last_input = now;
+        let was = self.state;
+        match self.switch(IdleState::Active) {
+            None => Input::Used,
+            Some(_) if was == IdleState::Off => Input::Woke,
+            Some(_) => Input::Brightened,
+        }
+    }
+
+    /// The state the screen is due for, if that changed.
+    pub fn poll(&mut self, now: Instant) -> Option<IdleState> {
+        let idle = now.saturating_duration_since(self.last_input);
+        let state = if idle >= self.off_after {
+            IdleState::Off
+        } else if idle >= self.dim_after {
+            IdleState::Dimmed
+        } else {
+            IdleState::Active
+        };
+        self.switch(state)
+    }
+
+    /// When [`Self::poll`] has something new, `None` once the screen is off.
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.state {
+            IdleState::Active => Some(self.last_input + self.dim_after.min(self.off_after)),
+            IdleState::Dimmed => Some(self.last_input + self.off_after),
+            IdleState::Off => None,
+        }
+    }
+
+    fn switch(&mut self, state: IdleState) -> Option<IdleState> {
+        if state == self.state {
+            return None;
+        }
+        self.state = state;
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> Instant {
+        Instant::from_secs(secs)
+    }
+
+    fn timer() -> IdleTimer {
+        IdleTimer::new(DIM_AFTER, OFF_AFTER, at(0))
+    }
+
+    #[test]
+    fn active_dimmed_off() {
+        let mut idle = timer();
+        assert_eq!(idle.deadline(), Some(at(0) + DIM_AFTER));
+        assert_eq!(idle.poll(at(1)), None);
+        assert_eq!(idle.poll(at(0) + DIM_AFTER), Some(IdleState::Dimmed));
+        assert_eq!(idle.poll(at(0) + DIM_AFTER), None);
+        assert_eq!(idle.deadline(), Some(at(0) + OFF_AFTER));
+        assert_eq!(idle.poll(at(0) + OFF_AFTER), Some(IdleState::Off));
+        assert_eq!(idle.state(), IdleState::Off);
+        assert_eq!(idle.deadline(), None);
+    }
+
+    #[test]
+    fn straight_to_off_after_a_long_gap() {
+        let mut idle = timer();
+        assert_eq!(idle.poll(at(0) + OFF_AFTER), Some(IdleState::Off));
+    }
+
+    #[test]
+    fn input_restarts_the_countdown() {
+        let mut idle = timer();
+        assert_eq!(idle.input(at(15)), Input::Used);
+        assert_eq!(idle.poll(at(0) + DIM_AFTER), None);
+        assert_eq!(idle.deadline(), Some(at(15) + DIM_AFTER));
+    }
+
+    #[test]
+    fn input_brightens_a_dimmed_screen() {
+        let mut idle = timer();
+        idle.poll(at(0) + DIM_AFTER);
+        assert_eq!(idle.input(at(30)), Input::Brightened);
+        assert_eq!(idle.state(), IdleState::Active);
+        assert_eq!(idle.input(at(31)), Input::Used);
+    }
+
+    #[test]
+    fn first_input_after_off_only_wakes() {
+        let mut idle = timer();
+        idle.poll(at(0) + OFF_AFTER);
+        assert_eq!(idle.input(at(100)), Input::Woke);
+        assert_eq!(idle.state(), IdleState::Active);
+        // the release and whatever comes next go through
+        assert_eq!(idle.input(at(100)), Input::Used);
+        assert_eq!(idle.poll(at(100) + DIM_AFTER), Some(IdleState::Dimmed));
+    }
+
+    #[test]
+    fn backlight_levels() {
+        assert_eq!(IdleState::Active.backlight(80), 80);
+        assert_eq!(IdleState::Dimmed.backlight(80), DIM_LEVEL);
+        assert_eq!(IdleState::Dimmed.backlight(2), 2);
+        assert_eq!(IdleState::Off.backlight(80), 0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Button gestures out of raw [`Event::ButtonDown`]/[`Event::ButtonUp`].
+//!
+//! The [`Recognizer`] is a plain state machine: it's fed presses, releases and the time,
+//! and never looks at a clock itself. Long presses and repeats fire while a button is held,
+//! so it has to be [`Recognizer::poll`]ed now and then, the app does that on every event.
+//!
+//! [`Event::ButtonDown`]: crate::events::Event::ButtonDown
+//! [`Event::ButtonUp`]: crate::events::Event::ButtonUp
+
+use embassy_time::{Duration, Instant};
+
+use crate::events::Button;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// Pressed and released before it became a long press.
+    Click(Button),
+    /// The second of two quick clicks, the first one came as a [`Gesture::Click`].
+    DoubleClick(Button),
+    /// Held for [`Thresholds::long_press`], fires while the button is still down.
+    LongPress(Button),
+    /// Every [`Thresholds::repeat`] while held after a long press.
+    Repeat(Button),
+    /// The second button went down right after the first, in that order. Neither clicks.
+    Chord(Button, Button),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Held this long, it's a long press instead of a click.
+    pub long_press: Duration,
+    /// A click released within this of the last one is a double click.
+    pub double_click: Duration,
+    pub repeat: Duration,
+    /// Two buttons pressed within this of each other are a chord.
+    pub chord: Duration,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            long_press: Duration::from_millis(500),
+            double_click: Duration::from_millis(300),
+            repeat: Duration::from_millis(200),
+            chord: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ButtonState {
+    /// `None` while the button is up.
+    pressed: Option<Instant>,
+    /// A long press or chord already came out of this press, the release isn't a click.
+    consumed: bool,
+    /// Due time of the next [`Gesture::Repeat`], once the long press fired.
+    next_repeat: Option<Instant>,
+    last_click: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Recognizer {
+    thresholds: Thresholds,
+    buttons: [ButtonState; 3],
+}
+
+impl Recognizer {
+    pub fn new(thresholds: Thresholds) -> Self {
+        Self {
+            thresholds,
+            buttons: [ButtonState::default(); 3],
+        }
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        self.thresholds
+    }
+
+    pub fn press(&mut self, button: Button, now: Instant) -> Option<Gesture> {
+        let chord = self.thresholds.chord;
+        let first = [Button::A, Button::B, Button::C].into_iter().find(|other| {
+            let state = &self.buttons[*other as usize];
+            *other != button
+                && !state.consumed
+                && state
+                    .pressed
+                    .is_some_and(|pressed| now.saturating_duration_since(pressed) <= chord)
+        });
+
+        let state = &mut self.buttons[button as usize];
+        // a press without a release in between, the release got lost
+        if state.pressed.is_some() {
+            return None;
+        }
+        state.pressed = Some(now);
+        state.consumed = false;
+        state.next_repeat = None;
+
+        let first = first?;
+        for button in [first, button] {
+            let state = &mut self.buttons[button as usize];
+            state.consumed = true;
+            state.last_click = None;
+        }
+        Some(Gesture::Chord(first, button))
+    }
+
+    pub fn release(&mut self, button: Button, now: Instant) -> Option<Gesture> {
+        let thresholds = self.thresholds;
+        let state = &mut self.buttons[button as usize];
+        let pressed = state.pressed.take()?;
+        state.next_repeat = None;
+        if state.consumed || now.saturating_duration_since(pressed) >= thresholds.long_press {
+            state.last_click = None;
+            return None;
+        }
+
+        let double = state
+            .last_click
+            .is_some_and(|last| now.saturating_duration_since(last) <= thresholds.double_click);
+        if double {
+            state.last_click = None;
+            Some(Gesture::DoubleClick(button))
+        } else {
+            state.last_click = Some(now);
+            Some(Gesture::Click(button))
+        }
+    }
+
+    /// Long presses and repeats that are due, at most one per call.
+    pub fn poll(&mut self, now: Instant) -> Option<Gesture> {
+        let thresholds = self.thresholds;
+        for button in [Button::A, Button::B, Button::C] {
+            let state = &mut self.buttons[button as usize];
+            let Some(pressed) = state.pressed else {
+                continue;
+            };
+
+            match state.next_repeat {
+                None if !state.consumed
+                    && now.saturating_duration_since(pressed) >= thresholds.long_press =>
+                {
+                    state.consumed = true;
+                    state.last_click = None;
+                    state.next_repeat = Some(now + thresholds.repeat);
+                    return Some(Gesture::LongPress(button));
+                }
+                Some(due) if now >= due => {
+                    // no catching up on missed repeats
+                    state.next_repeat = Some(now + thresholds.repeat);
+                    return Some(Gesture::Repeat(button));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// How long the button has been down, `None` while it's up or after [`Self::cancel`].
+    pub fn held(&self, button: Button, now: Instant) -> Option<Duration> {
+        self.buttons[button as usize]
+            .pressed
+            .map(|pressed| now.saturating_duration_since(pressed))
+    }
+
+    /// Forgets the buttons held right now, nothing comes of them until they're pressed again.
+    pub fn cancel(&mut self) {
+        self.buttons = [ButtonState::default(); 3];
+    }
+}
+
+impl Default for Recognizer {
+    fn default() -> Self {
+        Self::new(Thresholds::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> Instant {
+        Instant::from_millis(ms)
+    }
+
+    #[test]
+    fn click() {
+        let mut input = Recognizer::default();
+        assert_eq!(input.press(Button::A, at(0)), None);
+        assert_eq!(input.poll(at(100)), None);
+        assert_eq!(
+            input.release(Button::A, at(150)),
+            Some(Gesture::Click(Button::A))
+        );
+    }
+
+    #[test]
+    fn double_click() {
+        let mut input = Recognizer::default();
+        input.press(Button::B, at(0));
+        assert_eq!(
+            input.release(Button::B, at(100)),
+            Some(Gesture::Click(Button::B))
+        );
+        input.press(Button::B, at(200));
+        assert_eq!(
+            input.release(Button::B, at(300)),
+            Some(Gesture::DoubleClick(Button::B))
+        );
+        // a third click starts over
+        input.press(Button::B, at(400));
+        assert_eq!(
+            input.release(Button::B, at(450)),
+            Some(Gesture::Click(Button::B))
+        );
+    }
+
+    #[test]
+    fn slow_second_click() {
+        let mut input = Recognizer::default();
+        input.press(Button::A, at(0));
+        input.release(Button::A, at(100));
+        input.press(Button::A, at(500));
+        assert_eq!(
+            input.release(Button::A, at(550)),
+            Some(Gesture::Click(Button::A))
+        );
+    }
+
+    #[test]
+    fn long_press_and_repeat() {
+        let mut input = Recognizer::default();
+        input.press(Button::A, at(0));
+        assert_eq!(input.poll(at(499)), None);
+        assert_eq!(input.poll(at(500)), Some(Gesture::LongPress(Button::A)));
+        assert_eq!(input.poll(at(600)), None);
+        assert_eq!(input.poll(at(700)), Some(Gesture::Repeat(Button::A)));
+        // missed repeats aren't caught up on
+        assert_eq!(input.poll(at(1500)), Some(Gesture::Repeat(Button::A)));
+        assert_eq!(input.poll(at(1600)), None);
+        // the release after a long press isn't a click
+        assert_eq!(input.release(Button::A, at(1650)), None);
+        assert_eq!(input.poll(at(2000)), None);
+    }
+
+    #[test]
+    fn long_press_without_poll() {
+        let mut input = Recognizer::default();
+        input.press(Button::C, at(0));
+        assert_eq!(input.release(Button::C, at(600)), None);
+    }
+
+    #[test]
+    fn chord_a_then_b() {
+        let mut input = Recognizer::default();
+        assert_eq!(input.press(Button::A, at(0)), None);
+        assert_eq!(
+            input.press(Button::B, at(50)),
+            Some(Gesture::Chord(Button::A, Button::B))
+        );
+        // neither clicks nor long-presses
+        assert_eq!(input.poll(at(600)), None);
+        assert_eq!(input.release(Button::A, at(650)), None);
+        assert_eq!(input.release(Button::B, at(660)), None);
+    }
+
+    #[test]
+    fn chord_b_then_a() {
+        let mut input = Recognizer::default();
+        input.press(Button::B, at(0));
+        assert_eq!(
+            input.press(Button::A, at(100)),
+            Some(Gesture::Chord(Button::B, Button::A))
+        );
+        assert_eq!(input.release(Button::B, at(150)), None);
+        assert_eq!(input.release(Button::A, at(160)), None);
+    }
+
+    #[test]
+    fn too_slow_for_a_chord() {
+        let mut input = Recognizer::default();
+        input.press(Button::A, at(0));
+        assert_eq!(input.press(Button::B, at(150)), None);
+        assert_eq!(
+            input.release(Button::B, at(200)),
+            Some(Gesture::Click(Button::B))
+        );
+        assert_eq!(
+            input.release(Button::A, at(250)),
+            Some(Gesture::Click(Button::A))
+        );
+    }
+
+    #[test]
+    fn lost_release() {
+        let mut input = Recognizer::default();
+        input.press(Button::A, at(0));
+        // the release never came, the press still counts from the first one
+        assert_eq!(input.press(Button::A, at(100)), None);
+        assert_eq!(
+            input.held(Button::A, at(150)),
+            Some(Duration::from_millis(150))
+        );
+        assert_eq!(input.poll(at(500)), Some(Gesture::LongPress(Button::A)));
+        assert_eq!(input.release(Button::A, at(550)), None);
+        assert_eq!(input.held(Button::A, at(600)), None);
+    }
+
+    #[test]
+    fn release_without_press() {
+        let mut input = Recognizer::default();
+        assert_eq!(input.release(Button::B, at(0)), None);
+    }
+
+    #[test]
+    fn cancel() {
+        let mut input = Recognizer::default();
+        input.press(Button::C, at(0));
+        input.press(Button::A, at(10));
+        input.cancel();
+        assert_eq!(input.held(Button::C, at(100)), None);
+        assert_eq!(input.poll(at(600)), None);
+        assert_eq!(input.release(Button::C, at(700)), None);
+        assert_eq!(input.release(Button::A, at(700)), None);
+        // the next press is a fresh one
+        input.press(Button::C, at(800));
+        assert_eq!(
+            input.release(Button::C, at(850)),
+            Some(Gesture::Click(Button::C))
+        );
+    }
+}
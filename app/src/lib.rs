@@ -5,10 +5,32 @@
 extern crate alloc;
 
 pub mod app;
+pub mod auth;
+pub mod backlight;
+pub mod battery;
+pub mod clock;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod discovery;
 pub mod events;
+pub mod ha;
+pub mod idle;
+pub mod input;
 pub mod layout;
 pub mod logger;
+pub mod monitor;
+pub mod mqtt;
+pub mod ota;
+pub mod overlay;
+pub mod pager;
 pub mod remote;
+pub mod role;
+pub mod rpc;
+pub mod screens;
+pub mod settings;
+pub mod sleep;
+pub mod syslog;
 
-pub use app::App;
+pub use app::{App, Exit};
 pub use events::*;
+pub use role::Role;
@@ -3,14 +3,35 @@ use alloc::{collections::vec_deque::VecDeque, string::String, vec::Vec};
 #[cfg(feature = "std")]
 use std::{collections::VecDeque, string::String, vec::Vec};
 
-use crate::events::{EVENTS, Event};
+use crate::{
+    clock,
+    events::{EVENTS, Event},
+};
 use core::cell::RefCell;
 use critical_section::Mutex;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::Instant;
 use log::Level;
 
 static LOGGER: Logger = Logger;
 const LOGGER_CAPACITY: usize = 20;
-static LOGS: Mutex<RefCell<Option<VecDeque<(Level, String)>>>> = Mutex::new(RefCell::new(None));
+static LOGS: Mutex<RefCell<Option<VecDeque<LogEntry>>>> = Mutex::new(RefCell::new(None));
+
+/// Sequence number, level and message.
+pub type LogEntry = (u32, Level, String);
+
+/// Records waiting for the network sink, `None` while the sink is disabled.
+static REMOTE: Mutex<RefCell<Option<VecDeque<RemoteRecord>>>> = Mutex::new(RefCell::new(None));
+static REMOTE_CAPACITY: Mutex<RefCell<usize>> = Mutex::new(RefCell::new(0));
+static REMOTE_PENDING: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+#[derive(Debug, Clone)]
+pub struct RemoteRecord {
+    pub level: Level,
+    pub target: String,
+    pub msg: String,
+    pub uptime_ms: u64,
+}
 
 pub struct Logger;
 
@@ -31,13 +52,53 @@ pub fn latest_log_lines(limit: usize) -> Vec<(Level, String)> {
                     .rev()
                     .take(limit)
                     .rev()
-                    .map(|(level, msg)| (level.clone(), msg.clone()))
+                    .map(|(_, level, msg)| (level.clone(), msg.clone()))
                     .collect()
             })
             .unwrap_or_default()
     })
 }
 
+/// Starts buffering records for a network sink (e.g. syslog).
+///
+/// Call it early: everything logged from here on is kept, up to `capacity` records,
+/// until the sink drains it with [`take_remote_records`]. Oldest records are dropped first.
+pub fn enable_remote_sink(capacity: usize) {
+    critical_section::with(|cs| {
+        REMOTE
+            .borrow_ref_mut(cs)
+            .replace(VecDeque::with_capacity(capacity));
+        *REMOTE_CAPACITY.borrow_ref_mut(cs) = capacity;
+    });
+}
+
+/// Waits until there is something to send, then takes all buffered records.
+pub async fn take_remote_records() -> Vec<RemoteRecord> {
+    loop {
+        REMOTE_PENDING.wait().await;
+        let records = critical_section::with(|cs| {
+            REMOTE
+                .borrow_ref_mut(cs)
+                .as_mut()
+                .map(|records| records.drain(..).collect::<Vec<_>>())
+                .unwrap_or_default()
+        });
+        if !records.is_empty() {
+            return records;
+        }
+    }
+}
+
+/// Log lines with a sequence number greater than `seq`, oldest first.
+pub fn log_lines_after(seq: u32) -> Vec<LogEntry> {
+    critical_section::with(|cs| {
+        LOGS.borrow_ref(cs)
+            .as_ref()
+            .map(|logs| logs.iter().filter(|(s, _, _)| *s > seq).cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
 impl log::Log for Logger {
     fn enabled(&self, _metadata: &log::Metadata) -> bool {
         true
@@ -55,17 +116,36 @@ impl log::Log for Logger {
                 Level::Trace => ("\x1b[35m", "\x1b[0m"), // magenta
             };
 
+            #[allow(unused)]
+            let time = clock::now_local()
+                .map(|t| format!("{:02}:{:02}:{:02} ", t.hour, t.minute, t.second))
+                .unwrap_or_default();
+
             #[cfg(feature = "alloc")]
-            esp_println::println!("{}[{}]{} {}", color, record.level(), reset, &msg);
+            esp_println::println!("{}{}[{}]{} {}", time, color, record.level(), reset, &msg);
             #[cfg(feature = "std")]
-            println!("{}[{}]{} {}", color, record.level(), reset, &msg);
+            println!("{}{}[{}]{} {}", time, color, record.level(), reset, &msg);
 
             critical_section::with(|cs| {
                 if let Some(logs) = LOGS.borrow_ref_mut(cs).as_mut() {
+                    let seq = logs.back().map_or(1, |(seq, _, _)| seq + 1);
                     if logs.len() == LOGGER_CAPACITY {
                         logs.pop_front();
                     }
-                    logs.push_back((record.level(), msg));
+                    logs.push_back((seq, record.level(), msg.clone()));
+                }
+
+                if let Some(records) = REMOTE.borrow_ref_mut(cs).as_mut() {
+                    if records.len() == *REMOTE_CAPACITY.borrow_ref(cs) {
+                        records.pop_front();
+                    }
+                    records.push_back(RemoteRecord {
+                        level: record.level(),
+                        target: record.target().into(),
+                        msg,
+                        uptime_ms: Instant::now().as_millis(),
+                    });
+                    REMOTE_PENDING.signal(());
                 }
             });
             _ = EVENTS.immediate_publisher().try_publish(Event::LogAdded);
@@ -0,0 +1,125 @@
+//! Frame counting for the monitor tab, fed raw 802.11 frames by the platform's sniffer.
+//!
+//! [`Monitor`] has no allocations in [`Monitor::record`] so it can sit in a static and be
+//! fed from the radio driver's callback.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use crate::events::{PacketStats, Talker};
+
+/// Sources tracked at once, the quietest one makes room for a new one.
+pub const MAX_TALKERS: usize = 32;
+/// Talkers in [`PacketStats`].
+pub const TOP_TALKERS: usize = 8;
+/// 2.4 GHz channels 1 to 14.
+pub const CHANNELS: usize = 14;
+
+const ESPRESSIF_OUI: [u8; 3] = [0x18, 0xfe, 0x34];
+/// Action frame category of ESP-NOW.
+const VENDOR_SPECIFIC: u8 = 127;
+
+/// Transmitter address, control frames like ACK and CTS don't have one.
+pub fn source(frame: &[u8]) -> Option<[u8; 6]> {
+    let frame_type = (frame.first()? >> 2) & 0b11;
+    if frame_type == 1 {
+        return None;
+    }
+    frame.get(10..16)?.try_into().ok()
+}
+
+/// ESP-NOW goes out as vendor-specific action frames with Espressif's OUI.
+pub fn is_esp_now(frame: &[u8]) -> bool {
+    // management frame, subtype action
+    frame.first() == Some(&0xd0)
+        && frame.get(24) == Some(&VENDOR_SPECIFIC)
+        && frame.get(25..28) == Some(&ESPRESSIF_OUI[..])
+}
+
+pub struct Monitor {
+    channels: [u32; CHANNELS],
+    frames: u32,
+    esp_now: u32,
+    talkers: [Option<Talker>; MAX_TALKERS],
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Monitor {
+    pub const fn new() -> Self {
+        Self {
+            channels: [0; CHANNELS],
+            frames: 0,
+            esp_now: 0,
+            talkers: [None; MAX_TALKERS],
+        }
+    }
+
+    pub fn record(&mut self, channel: u8, rssi: i8, frame: &[u8]) {
+        let esp_now = is_esp_now(frame);
+        self.frames += 1;
+        self.esp_now += esp_now as u32;
+        if let Some(count) = self.channels.get_mut((channel as usize).wrapping_sub(1)) {
+            *count += 1;
+        }
+
+        let Some(mac) = source(frame) else {
+            return;
+        };
+        let slot = match self
+            .talkers
+            .iter()
+            .position(|talker| talker.is_some_and(|talker| talker.mac == mac))
+        {
+            Some(i) => i,
+            None => {
+                let i = self
+                    .talkers
+                    .iter()
+                    .position(Option::is_none)
+                    .or_else(|| {
+                        self.talkers
+                            .iter()
+                            .enumerate()
+                            .min_by_key(|(_, talker)| talker.map(|talker| talker.frames))
+                            .map(|(i, _)| i)
+                    })
+                    .unwrap_or_default();
+                self.talkers[i] = Some(Talker {
+                    mac,
+                    ..Default::default()
+                });
+                i
+            }
+        };
+        if let Some(talker) = &mut self.talkers[slot] {
+            talker.frames += 1;
+            talker.esp_now += esp_now as u32;
+            talker.rssi = rssi;
+        }
+    }
+
+    /// Counts since the last call, talkers since the start.
+    pub fn take_stats(&mut self) -> PacketStats {
+        let mut talkers = self.talkers.iter().flatten().copied().collect::<Vec<_>>();
+        talkers.sort_by_key(|talker| core::cmp::Reverse(talker.frames));
+        talkers.truncate(TOP_TALKERS);
+
+        let stats = PacketStats {
+            frames: self.frames,
+            esp_now: self.esp_now,
+            channels: self.channels,
+            talkers,
+        };
+        self.frames = 0;
+        self.esp_now = 0;
+        self.channels = [0; CHANNELS];
+        stats
+    }
+}
@@ -0,0 +1,78 @@
+//! Mapping between app events and MQTT topics/payloads.
+//!
+//! Topics are relative to the device base topic, e.g. `stick/stick-a1b2c3`.
+//!
+//! Telemetry:
+//! - `stats` - `{"battery":87,"battery_mv":3920,"power":"battery","minutes_left":190,"heap_used":1024,"heap_free":4096}`,
+//!   `power` is `battery`, `charging` or `usb`, `minutes_left` is `null` until it's known
+//! - `button/a`, `button/b`, `button/c` - `pressed` / `released`
+//! - `joystick` - `{"x":-40,"y":100}`
+//! - `joystick/button` - `pressed`
+//!
+//! Commands (`cmd/#`):
+//! - `cmd/remote` - remote key, e.g. `volume-up`
+//! - `cmd/vehicle` - `left,right`, e.g. `50,-50`
+//! - `cmd/tab` - tab title, e.g. `tv`
+
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use std::string::{String, ToString};
+
+use crate::events::{Button, Event, JoyC, Vehicle};
+
+pub const STATUS_TOPIC: &str = "status";
+pub const ONLINE: &str = "online";
+pub const OFFLINE: &str = "offline";
+pub const COMMAND_TOPICS: &str = "cmd/#";
+
+pub fn button_topic(button: Button) -> &'static str {
+    match button {
+        Button::A => "button/a",
+        Button::B => "button/b",
+        Button::C => "button/c",
+    }
+}
+
+/// Topic and payload to publish for an app event, if it's telemetry.
+pub fn telemetry(event: &Event) -> Option<(&'static str, String)> {
+    match event {
+        Event::StatsUpdated(stats) => Some((
+            "stats",
+            format!(
+                r#"{{"battery":{},"battery_mv":{},"power":"{}","minutes_left":{},"heap_used":{},"heap_free":{}}}"#,
+                stats.battery_level,
+                stats.battery_mv,
+                <&str>::from(stats.power),
+                stats
+                    .minutes_left
+                    .map(|minutes| minutes.to_string())
+                    .unwrap_or("null".into()),
+                stats.heap_used,
+                stats.heap_free
+            ),
+        )),
+        Event::ButtonDown(button) => Some((button_topic(*button), "pressed".into())),
+        Event::ButtonUp(button) => Some((button_topic(*button), "released".into())),
+        Event::JoyC(JoyC::Pos((x, y))) => Some(("joystick", format!(r#"{{"x":{},"y":{}}}"#, x, y))),
+        Event::JoyC(JoyC::Button) => Some(("joystick/button", "pressed".into())),
+        _ => None,
+    }
+}
+
+/// App event for a message on one of the [`COMMAND_TOPICS`].
+pub fn command(topic: &str, payload: &[u8]) -> Option<Event> {
+    let payload = core::str::from_utf8(payload).ok()?.trim();
+
+    match topic {
+        "cmd/remote" => payload.parse().ok().map(Event::Remote),
+        "cmd/vehicle" => {
+            let (left, right) = payload.split_once(',')?;
+            let left = left.trim().parse::<i8>().ok()?.clamp(-100, 100);
+            let right = right.trim().parse::<i8>().ok()?.clamp(-100, 100);
+            Some(Event::Vehicle(Vehicle::Move(left, right)))
+        }
+        "cmd/tab" => Some(Event::SelectTab(payload.into())),
+        _ => None,
+    }
+}
@@ -0,0 +1,502 @@
+//! Over-the-air update receiver.
+//!
+//! The image arrives in sequential chunks, each with a CRC-32. The partition is erased a
+//! sector at a time ahead of the chunks, so no request blocks for a whole-partition erase.
+//! The whole image is checked against a SHA-256 before the new partition is activated.
+//! Flash access goes through [`OtaFlash`], so the state machine runs on the host with
+//! [`MemFlash`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use std::{boxed::Box, vec::Vec};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Chunk payload size that fits a [`crate::rpc::MAX_PACKET_SIZE`] datagram.
+pub const CHUNK_SIZE: usize = 768;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OtaError {
+    /// No update in progress.
+    NotStarted,
+    TooLarge,
+    /// Chunk doesn't continue the image, the sender should resume at `expected`.
+    UnexpectedOffset {
+        expected: u32,
+    },
+    ChunkCrc,
+    Incomplete,
+    Sha256Mismatch,
+    Flash,
+}
+
+/// The inactive OTA partition.
+pub trait OtaFlash {
+    fn capacity(&self) -> u32;
+    /// Smallest erasable unit, the capacity is a multiple of it.
+    fn erase_size(&self) -> u32;
+    /// Erases `len` bytes at `offset`, both multiples of [`OtaFlash::erase_size`].
+    fn erase(&mut self, offset: u32, len: u32) -> Result<(), OtaError>;
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), OtaError>;
+    /// Makes the partition the boot partition, in a pending-verify state.
+    fn activate(&mut self) -> Result<(), OtaError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OtaState {
+    Idle,
+    Receiving {
+        size: u32,
+        written: u32,
+        /// Everything below is erased.
+        erased: u32,
+    },
+    /// Activated, waiting for a reboot.
+    Done,
+}
+
+pub struct OtaReceiver {
+    flash: Box<dyn OtaFlash>,
+    state: OtaState,
+    sha256: [u8; 32],
+    hasher: Sha256,
+}
+
+impl OtaReceiver {
+    pub fn new(flash: impl OtaFlash + 'static) -> Self {
+        Self {
+            flash: Box::new(flash),
+            state: OtaState::Idle,
+            sha256: [0; 32],
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub fn state(&self) -> OtaState {
+        self.state
+    }
+
+    /// Starts (or restarts) an update of `size` bytes.
+    pub fn begin(&mut self, size: u32, sha256: [u8; 32]) -> Result<(), OtaError> {
+        if size > self.flash.capacity() {
+            return Err(OtaError::TooLarge);
+        }
+        self.sha256 = sha256;
+        self.hasher = Sha256::new();
+        self.state = OtaState::Receiving {
+            size,
+            written: 0,
+            erased: 0,
+        };
+        Ok(())
+    }
+
+    /// Writes a chunk, returns the offset of the next one.
+    ///
+    /// Chunks must come in order. A repeated chunk (the sender didn't get the ack)
+    /// is acknowledged again without writing. A write that fails may have written part of
+    /// the chunk, which can't be written over without erasing what came before it, so the
+    /// update has to start over with [`OtaReceiver::begin`].
+    pub fn chunk(&mut self, offset: u32, crc: u32, data: &[u8]) -> Result<u32, OtaError> {
+        let OtaState::Receiving {
+            size,
+            written,
+            mut erased,
+        } = self.state
+        else {
+            return Err(OtaError::NotStarted);
+        };
+        let end = u32::try_from(data.len())
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .ok_or(OtaError::TooLarge)?;
+
+        if offset < written && end == written {
+            return Ok(written);
+        }
+        if offset != written {
+            return Err(OtaError::UnexpectedOffset { expected: written });
+        }
+        if end > size {
+            return Err(OtaError::TooLarge);
+        }
+        if crc32(data) != crc {
+            return Err(OtaError::ChunkCrc);
+        }
+
+        if end > erased {
+            let to = end.next_multiple_of(self.flash.erase_size());
+            self.flash.erase(erased, to - erased)?;
+            erased = to;
+            self.state = OtaState::Receiving {
+                size,
+                written,
+                erased,
+            };
+        }
+        if let Err(e) = self.flash.write(offset, data) {
+            self.state = OtaState::Idle;
+            return Err(e);
+        }
+        self.hasher.update(data);
+
+        self.state = OtaState::Receiving {
+            size,
+            written: end,
+            erased,
+        };
+        Ok(end)
+    }
+
+    /// Verifies the whole image and activates it.
+    ///
+    /// If the activation fails the image stays received, so `finish` can be retried.
+    pub fn finish(&mut self) -> Result<(), OtaError> {
+        let (size, written) = match self.state {
+            OtaState::Receiving { size, written, .. } => (size, written),
+            // repeated finish, the sender didn't get the response
+            OtaState::Done => return Ok(()),
+            OtaState::Idle => return Err(OtaError::NotStarted),
+        };
+        if written != size {
+            return Err(OtaError::Incomplete);
+        }
+
+        let digest: [u8; 32] = self.hasher.clone().finalize().into();
+        if digest != self.sha256 {
+            self.state = OtaState::Idle;
+            return Err(OtaError::Sha256Mismatch);
+        }
+
+        self.flash.activate()?;
+        self.state = OtaState::Done;
+        Ok(())
+    }
+}
+
+/// CRC-32 (IEEE 802.3), the one zlib and `crc32` use.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// In-memory [`OtaFlash`] for running the receiver on the host.
+///
+/// Like NOR flash, it only takes writes to erased bytes.
+#[derive(Debug, Default)]
+pub struct MemFlash {
+    pub data: Vec<u8>,
+    pub activated: bool,
+    /// Makes [`OtaFlash::activate`] fail, for testing.
+    pub fail_activate: bool,
+    /// Makes [`OtaFlash::write`] fail halfway through, for testing.
+    pub fail_write: bool,
+}
+
+impl MemFlash {
+    pub const ERASE_SIZE: u32 = 4096;
+
+    /// `capacity` is rounded up to whole sectors.
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            data: vec![0; capacity.next_multiple_of(Self::ERASE_SIZE) as usize],
+            activated: false,
+            fail_activate: false,
+            fail_write: false,
+        }
+    }
+}
+
+impl OtaFlash for MemFlash {
+    fn capacity(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    fn erase_size(&self) -> u32 {
+        Self::ERASE_SIZE
+    }
+
+    fn erase(&mut self, offset: u32, len: u32) -> Result<(), OtaError> {
+        if !offset.is_multiple_of(Self::ERASE_SIZE) || !len.is_multiple_of(Self::ERASE_SIZE) {
+            return Err(OtaError::Flash);
+        }
+        let offset = offset as usize;
+        self.data
+            .get_mut(offset..offset + len as usize)
+            .ok_or(OtaError::Flash)?
+            .fill(0xFF);
+        self.activated = false;
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), OtaError> {
+        let offset = offset as usize;
+        let target = self
+            .data
+            .get_mut(offset..offset + data.len())
+            .ok_or(OtaError::Flash)?;
+        if target.iter().any(|&byte| byte != 0xFF) {
+            return Err(OtaError::Flash);
+        }
+        if self.fail_write {
+            let half = data.len() / 2;
+            target[..half].copy_from_slice(&data[..half]);
+            return Err(OtaError::Flash);
+        }
+        target.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn activate(&mut self) -> Result<(), OtaError> {
+        if self.fail_activate {
+            return Err(OtaError::Flash);
+        }
+        self.activated = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+    #[cfg(feature = "std")]
+    use std::rc::Rc;
+
+    /// Lets the test look at the flash the receiver owns.
+    #[derive(Clone)]
+    struct Shared(Rc<RefCell<MemFlash>>);
+
+    impl OtaFlash for Shared {
+        fn capacity(&self) -> u32 {
+            self.0.borrow().capacity()
+        }
+        fn erase_size(&self) -> u32 {
+            self.0.borrow().erase_size()
+        }
+        fn erase(&mut self, offset: u32, len: u32) -> Result<(), OtaError> {
+            self.0.borrow_mut().erase(offset, len)
+        }
+        fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), OtaError> {
+            self.0.borrow_mut().write(offset, data)
+        }
+        fn activate(&mut self) -> Result<(), OtaError> {
+            self.0.borrow_mut().activate()
+        }
+    }
+
+    fn receiver(capacity: u32) -> (OtaReceiver, Shared) {
+        let flash = Shared(Rc::new(RefCell::new(MemFlash::new(capacity))));
+        (OtaReceiver::new(flash.clone()), flash)
+    }
+
+    fn image(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn send(ota: &mut OtaReceiver, image: &[u8]) -> Result<(), OtaError> {
+        for (i, chunk) in image.chunks(CHUNK_SIZE).enumerate() {
+            let offset = (i * CHUNK_SIZE) as u32;
+            ota.chunk(offset, crc32(chunk), chunk)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn chunk_before_begin() {
+        let (mut ota, _) = receiver(0x10000);
+        assert_eq!(ota.chunk(0, crc32(b"x"), b"x"), Err(OtaError::NotStarted));
+        assert_eq!(ota.finish(), Err(OtaError::NotStarted));
+    }
+
+    #[test]
+    fn begin_doesnt_erase() {
+        let (mut ota, flash) = receiver(0x10000);
+        ota.begin(0x8000, [0; 32]).unwrap();
+        assert_eq!(
+            ota.state(),
+            OtaState::Receiving {
+                size: 0x8000,
+                written: 0,
+                erased: 0
+            }
+        );
+        assert!(flash.0.borrow().data.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn in_order_chunks() {
+        let image = image(10_000);
+        let (mut ota, flash) = receiver(0x10000);
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        send(&mut ota, &image).unwrap();
+        ota.finish().unwrap();
+
+        assert_eq!(ota.state(), OtaState::Done);
+        let flash = flash.0.borrow();
+        assert!(flash.activated);
+        assert_eq!(&flash.data[..image.len()], &image[..]);
+        // erased up to the sector the image ends in, not further
+        assert!(flash.data[image.len()..3 * 4096].iter().all(|&b| b == 0xFF));
+        assert!(flash.data[3 * 4096..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn resent_chunk_is_acked_again() {
+        let image = image(2 * CHUNK_SIZE);
+        let (mut ota, _) = receiver(0x10000);
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        let first = &image[..CHUNK_SIZE];
+        assert_eq!(ota.chunk(0, crc32(first), first), Ok(CHUNK_SIZE as u32));
+        assert_eq!(ota.chunk(0, crc32(first), first), Ok(CHUNK_SIZE as u32));
+        let second = &image[CHUNK_SIZE..];
+        ota.chunk(CHUNK_SIZE as u32, crc32(second), second).unwrap();
+        ota.finish().unwrap();
+    }
+
+    #[test]
+    fn out_of_order_chunk() {
+        let image = image(3 * CHUNK_SIZE);
+        let (mut ota, _) = receiver(0x10000);
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        let third = &image[2 * CHUNK_SIZE..];
+        assert_eq!(
+            ota.chunk(2 * CHUNK_SIZE as u32, crc32(third), third),
+            Err(OtaError::UnexpectedOffset { expected: 0 })
+        );
+        // the sender resumes where it's told and the image still checks out
+        send(&mut ota, &image).unwrap();
+        ota.finish().unwrap();
+    }
+
+    #[test]
+    fn bad_crc() {
+        let image = image(CHUNK_SIZE);
+        let (mut ota, _) = receiver(0x10000);
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        assert_eq!(
+            ota.chunk(0, crc32(&image) ^ 1, &image),
+            Err(OtaError::ChunkCrc)
+        );
+        assert_eq!(ota.chunk(0, crc32(&image), &image), Ok(CHUNK_SIZE as u32));
+    }
+
+    #[test]
+    fn wrong_sha256() {
+        let image = image(5000);
+        let (mut ota, flash) = receiver(0x10000);
+        ota.begin(image.len() as u32, [0xAB; 32]).unwrap();
+        send(&mut ota, &image).unwrap();
+        assert_eq!(ota.finish(), Err(OtaError::Sha256Mismatch));
+        assert_eq!(ota.state(), OtaState::Idle);
+        assert!(!flash.0.borrow().activated);
+    }
+
+    #[test]
+    fn incomplete() {
+        let image = image(2 * CHUNK_SIZE);
+        let (mut ota, _) = receiver(0x10000);
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        send(&mut ota, &image[..CHUNK_SIZE]).unwrap();
+        assert_eq!(ota.finish(), Err(OtaError::Incomplete));
+    }
+
+    #[test]
+    fn oversize_image() {
+        let (mut ota, _) = receiver(0x10000);
+        assert_eq!(ota.begin(0x10001, [0; 32]), Err(OtaError::TooLarge));
+        assert_eq!(ota.state(), OtaState::Idle);
+
+        // a chunk past the announced size
+        ota.begin(100, [0; 32]).unwrap();
+        let data = image(101);
+        assert_eq!(ota.chunk(0, crc32(&data), &data), Err(OtaError::TooLarge));
+    }
+
+    #[test]
+    fn offset_overflow() {
+        let (mut ota, _) = receiver(0x10000);
+        ota.begin(0x10000, [0; 32]).unwrap();
+        let data = image(16);
+        assert_eq!(
+            ota.chunk(u32::MAX - 8, crc32(&data), &data),
+            Err(OtaError::TooLarge)
+        );
+    }
+
+    #[test]
+    fn failed_activate_can_be_retried() {
+        let image = image(3000);
+        let (mut ota, flash) = receiver(0x10000);
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        send(&mut ota, &image).unwrap();
+
+        flash.0.borrow_mut().fail_activate = true;
+        assert_eq!(ota.finish(), Err(OtaError::Flash));
+        assert!(matches!(
+            ota.state(),
+            OtaState::Receiving { written: 3000, .. }
+        ));
+
+        flash.0.borrow_mut().fail_activate = false;
+        ota.finish().unwrap();
+        assert_eq!(ota.state(), OtaState::Done);
+        assert!(flash.0.borrow().activated);
+        // a repeated finish is fine too
+        assert_eq!(ota.finish(), Ok(()));
+    }
+
+    #[test]
+    fn failed_write_starts_over() {
+        let image = image(3 * CHUNK_SIZE);
+        let (mut ota, flash) = receiver(0x10000);
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        let first = &image[..CHUNK_SIZE];
+        ota.chunk(0, crc32(first), first).unwrap();
+
+        flash.0.borrow_mut().fail_write = true;
+        let second = &image[CHUNK_SIZE..2 * CHUNK_SIZE];
+        let offset = CHUNK_SIZE as u32;
+        assert_eq!(
+            ota.chunk(offset, crc32(second), second),
+            Err(OtaError::Flash)
+        );
+        assert_eq!(ota.state(), OtaState::Idle);
+
+        // half of it is on the flash, retrying the chunk can't fix that
+        flash.0.borrow_mut().fail_write = false;
+        assert_eq!(
+            ota.chunk(offset, crc32(second), second),
+            Err(OtaError::NotStarted)
+        );
+
+        // starting over erases the sector again
+        ota.begin(image.len() as u32, sha256(&image)).unwrap();
+        send(&mut ota, &image).unwrap();
+        ota.finish().unwrap();
+        assert_eq!(&flash.0.borrow().data[..image.len()], &image[..]);
+    }
+}
@@ -0,0 +1,405 @@
+//! Toasts, confirm dialogs, pages and pairing PINs over whatever is on screen.
+//!
+//! Anything can raise one with [`Event::Toast`] or [`Event::Confirm`], [`Event::Page`] and
+//! [`Event::PairingPin`] raise the others. Toasts go away on their own and let the buttons
+//! through, a confirm dialog takes every button until it's answered and publishes its action
+//! on a yes. A page or a PIN stays up until the first button [`Overlays::dismiss`]es it. The
+//! newest one is on top.
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{boxed::Box, string::String, vec::Vec};
+
+use embassy_time::{Duration, Instant};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Margin, Rect},
+    prelude::Widget,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
+};
+
+use crate::{
+    auth::{PIN_TIMEOUT, PairingPin},
+    events::Event,
+    pager::Page,
+};
+
+/// How long a toast stays up.
+pub const TOAST_FOR: Duration = Duration::from_secs(2);
+/// Toasts on screen at once, the oldest goes first.
+const MAX_TOASTS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct Confirm {
+    /// A yes or no question, e.g. `really shut down?`.
+    pub prompt: String,
+    /// Goes out on the event bus when answered with yes.
+    pub action: Box<Event>,
+}
+
+impl Confirm {
+    pub fn new(prompt: impl Into<String>, action: Event) -> Self {
+        Self {
+            prompt: prompt.into(),
+            action: Box::new(action),
+        }
+    }
+}
+
+/// What [`Overlays::dismiss`] took off the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dismissed {
+    /// The newest page was read.
+    Page,
+    /// The user refused the pairing.
+    Pin,
+}
+
+#[derive(Debug, Clone)]
+enum Overlay {
+    Toast {
+        text: String,
+        until: Instant,
+    },
+    Confirm(Confirm),
+    /// The newest page.
+    Page(Page),
+    /// Until the host is paired, the PIN is refused or it expires.
+    Pin {
+        pin: PairingPin,
+        until: Instant,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Overlays {
+    stack: Vec<Overlay>,
+}
+
+impl Overlays {
+    /// The same text again only keeps the toast up longer.
+    pub fn toast(&mut self, text: String, now: Instant) {
+        let until = now + TOAST_FOR;
+        self.stack.retain(
+            |overlay| !matches!(overlay, Overlay::Toast { text: shown, .. } if *shown == text),
+        );
+        self.stack.push(Overlay::Toast { text, until });
+
+        let toasts = self.toasts().count();
+        if toasts > MAX_TOASTS {
+            let mut excess = toasts - MAX_TOASTS;
+            self.stack.retain(|overlay| {
+                let oldest = excess > 0 && matches!(overlay, Overlay::Toast { .. });
+                excess -= oldest as usize;
+                !oldest
+            });
+        }
+    }
+
+    pub fn confirm(&mut self, confirm: Confirm) {
+        self.stack.push(Overlay::Confirm(confirm));
+    }
+
+    /// Only the newest page is shown, it replaces the one before.
+    pub fn page(&mut self, page: Page) {
+        self.stack
+            .retain(|overlay| !matches!(overlay, Overlay::Page(_)));
+        self.stack.push(Overlay::Page(page));
+    }
+
+    /// Shows the PIN of the host pairing now, `None` takes it down.
+    pub fn pin(&mut self, pin: Option<PairingPin>, now: Instant) {
+        self.stack
+            .retain(|overlay| !matches!(overlay, Overlay::Pin { .. }));
+        if let Some(pin) = pin {
+            let until = now + PIN_TIMEOUT;
+            self.stack.push(Overlay::Pin { pin, until });
+        }
+    }
+
+    /// Takes down the toasts and PINs that are over.
+    pub fn expire(&mut self, now: Instant) {
+        self.stack.retain(|overlay| {
+            !matches!(overlay, Overlay::Toast { until, .. } | Overlay::Pin { until, .. } if *until <= now)
+        });
+    }
+
+    /// When the next toast or PIN is over.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.stack
+            .iter()
+            .filter_map(|overlay| match overlay {
+                Overlay::Toast { until, .. } | Overlay::Pin { until, .. } => Some(*until),
+                Overlay::Confirm(_) | Overlay::Page(_) => None,
+            })
+            .min()
+    }
+
+    /// A dialog on top is waiting for an answer, the buttons are its.
+    pub fn modal(&self) -> bool {
+        matches!(self.top(), Some(Overlay::Confirm(_)))
+    }
+
+    /// Closes the dialog on top, its action if the answer was yes.
+    pub fn answer(&mut self, yes: bool) -> Option<Event> {
+        if !self.modal() {
+            return None;
+        }
+        let Some(Overlay::Confirm(confirm)) = self.take_top() else {
+            unreachable!()
+        };
+        log::debug!("confirm '{}': {}", confirm.prompt, yes);
+        yes.then_some(*confirm.action)
+    }
+
+    /// Takes the page or PIN on top off the screen, if that's what is on top.
+    pub fn dismiss(&mut self) -> Option<Dismissed> {
+        let dismissed = match self.top()? {
+            Overlay::Page(_) => Dismissed::Page,
+            Overlay::Pin { .. } => Dismissed::Pin,
+            Overlay::Toast { .. } | Overlay::Confirm(_) => return None,
+        };
+        self.take_top();
+        Some(dismissed)
+    }
+
+    /// What the buttons do while something is on top.
+    pub fn footer(&self) -> Option<&'static str> {
+        match self.top()? {
+            Overlay::Confirm(_) => Some(" a - yes, b - no"),
+            Overlay::Page(_) => Some(" any button - dismiss"),
+            Overlay::Pin { .. } => Some(" any button - refuse"),
+            Overlay::Toast { .. } => None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        match self.top() {
+            Some(Overlay::Confirm(confirm)) => render_confirm(confirm, area, buf),
+            Some(Overlay::Page(page)) => render_page(page, area, buf),
+            Some(Overlay::Pin { pin, .. }) => render_pin(pin, area, buf),
+            Some(Overlay::Toast { .. }) | None => {}
+        }
+
+        // newest at the bottom, over the dialog if need be
+        let toasts = self.toasts().collect::<Vec<_>>();
+        let mut y = area.bottom();
+        for text in toasts.into_iter().rev() {
+            if y == area.top() {
+                break;
+            }
+            y -= 1;
+            let width = (text.chars().count() as u16 + 2).min(area.width);
+            let x = area.x + (area.width - width) / 2;
+            Paragraph::new(text)
+                .centered()
+                .style(Style::new().fg(Color::Black).bg(Color::Gray))
+                .render(Rect::new(x, y, width, 1), buf);
+        }
+    }
+
+    /// The newest overlay besides the toasts, which never hide it.
+    fn top(&self) -> Option<&Overlay> {
+        self.stack
+            .iter()
+            .rev()
+            .find(|overlay| !matches!(overlay, Overlay::Toast { .. }))
+    }
+
+    fn take_top(&mut self) -> Option<Overlay> {
+        let index = self
+            .stack
+            .iter()
+            .rposition(|overlay| !matches!(overlay, Overlay::Toast { .. }))?;
+        Some(self.stack.remove(index))
+    }
+
+    fn toasts(&self) -> impl Iterator<Item = &str> {
+        self.stack.iter().filter_map(|overlay| match overlay {
+            Overlay::Toast { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+    }
+}
+
+fn render_confirm(confirm: &Confirm, area: Rect, buf: &mut Buffer) {
+    // two lines of prompt
+    let [area] = Layout::vertical([Constraint::Length(4)])
+        .flex(Flex::Center)
+        .areas(area.inner(Margin::new(3, 0)));
+    Clear.render(area, buf);
+    Paragraph::new(confirm.prompt.as_str())
+        .style(Style::new().fg(Color::White))
+        .centered()
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::new()
+                .borders(Borders::ALL)
+                .border_style(Style::new().fg(Color::Yellow))
+                .padding(Padding::horizontal(1)),
+        )
+        .render(area, buf);
+}
+
+fn render_page(page: &Page, area: Rect, buf: &mut Buffer) {
+    let area = area.inner(Margin::new(2, 1));
+    Clear.render(area, buf);
+    Paragraph::new(page.text.as_str())
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::new()
+                .borders(Borders::ALL)
+                .border_style(Style::new().fg(Color::Yellow))
+                .title(format!(" {} ", page.from))
+                .padding(Padding::horizontal(1)),
+        )
+        .render(area, buf);
+}
+
+fn render_pin(pin: &PairingPin, area: Rect, buf: &mut Buffer) {
+    let area = area.inner(Margin::new(2, 1));
+    Clear.render(area, buf);
+    let digits = format!("{:06}", pin.pin);
+    Paragraph::new(vec![
+        Line::styled("pairing PIN", Style::new().fg(Color::Gray)),
+        Line::styled(
+            format!("{} {}", &digits[..3], &digits[3..]),
+            Style::new().fg(Color::White).bold(),
+        ),
+    ])
+    .centered()
+    .block(
+        Block::new()
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(Color::Cyan))
+            .title(format!(" {} ", pin.client))
+            .padding(Padding::top(1)),
+    )
+    .render(area, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> Instant {
+        Instant::from_secs(secs)
+    }
+
+    fn pin() -> PairingPin {
+        PairingPin {
+            client: "laptop".into(),
+            pin: 123456,
+        }
+    }
+
+    #[test]
+    fn toasts_expire() {
+        let mut overlays = Overlays::default();
+        overlays.toast("one".into(), at(0));
+        overlays.toast("two".into(), at(1));
+        assert_eq!(overlays.deadline(), Some(at(0) + TOAST_FOR));
+
+        overlays.expire(at(0) + TOAST_FOR);
+        assert_eq!(overlays.toasts().collect::<Vec<_>>(), ["two"]);
+        overlays.expire(at(1) + TOAST_FOR);
+        assert_eq!(overlays.toasts().count(), 0);
+        assert_eq!(overlays.deadline(), None);
+    }
+
+    #[test]
+    fn same_toast_again_stays_up_longer() {
+        let mut overlays = Overlays::default();
+        overlays.toast("hi".into(), at(0));
+        overlays.toast("hi".into(), at(1));
+        assert_eq!(overlays.toasts().count(), 1);
+        assert_eq!(overlays.deadline(), Some(at(1) + TOAST_FOR));
+    }
+
+    #[test]
+    fn oldest_toasts_go_first() {
+        let mut overlays = Overlays::default();
+        for text in ["1", "2", "3", "4", "5"] {
+            overlays.toast(text.into(), at(0));
+        }
+        assert_eq!(overlays.toasts().collect::<Vec<_>>(), ["3", "4", "5"]);
+    }
+
+    #[test]
+    fn confirm_yes_gives_the_action() {
+        let mut overlays = Overlays::default();
+        overlays.confirm(Confirm::new("off?", Event::PowerOff));
+        assert!(overlays.modal());
+        assert_eq!(overlays.footer(), Some(" a - yes, b - no"));
+        assert!(matches!(overlays.answer(true), Some(Event::PowerOff)));
+        assert!(!overlays.modal());
+        assert_eq!(overlays.footer(), None);
+    }
+
+    #[test]
+    fn confirm_no_gives_nothing() {
+        let mut overlays = Overlays::default();
+        overlays.confirm(Confirm::new("off?", Event::PowerOff));
+        assert!(overlays.answer(false).is_none());
+        assert!(!overlays.modal());
+        assert!(overlays.answer(true).is_none());
+    }
+
+    #[test]
+    fn toasts_dont_hide_a_dialog() {
+        let mut overlays = Overlays::default();
+        overlays.confirm(Confirm::new("off?", Event::PowerOff));
+        overlays.toast("hi".into(), at(0));
+        assert!(overlays.modal());
+        assert!(overlays.dismiss().is_none());
+        assert!(matches!(overlays.answer(true), Some(Event::PowerOff)));
+        assert_eq!(overlays.toasts().count(), 1);
+    }
+
+    #[test]
+    fn newest_is_on_top() {
+        let mut overlays = Overlays::default();
+        overlays.confirm(Confirm::new("off?", Event::PowerOff));
+        overlays.page(Page::new("desk", "lunch"));
+        // the page hides the dialog until it's dismissed
+        assert!(!overlays.modal());
+        assert!(overlays.answer(true).is_none());
+        assert_eq!(overlays.footer(), Some(" any button - dismiss"));
+        assert_eq!(overlays.dismiss(), Some(Dismissed::Page));
+        assert!(overlays.modal());
+        assert!(overlays.dismiss().is_none());
+        assert!(matches!(overlays.answer(true), Some(Event::PowerOff)));
+        assert!(overlays.top().is_none());
+    }
+
+    #[test]
+    fn newest_page_replaces_the_last() {
+        let mut overlays = Overlays::default();
+        overlays.page(Page::new("desk", "lunch"));
+        overlays.page(Page::new("desk", "now"));
+        assert!(matches!(overlays.top(), Some(Overlay::Page(page)) if page.text == "now"));
+        assert_eq!(overlays.dismiss(), Some(Dismissed::Page));
+        assert!(overlays.dismiss().is_none());
+    }
+
+    #[test]
+    fn pin_until_done_refused_or_expired() {
+        let mut overlays = Overlays::default();
+        overlays.pin(Some(pin()), at(0));
+        assert_eq!(overlays.footer(), Some(" any button - refuse"));
+        assert_eq!(overlays.deadline(), Some(at(0) + PIN_TIMEOUT));
+        overlays.pin(None, at(1));
+        assert!(overlays.top().is_none());
+
+        overlays.pin(Some(pin()), at(0));
+        assert_eq!(overlays.dismiss(), Some(Dismissed::Pin));
+        assert!(overlays.top().is_none());
+
+        overlays.pin(Some(pin()), at(0));
+        overlays.expire(at(0) + PIN_TIMEOUT);
+        assert!(overlays.top().is_none());
+    }
+}
@@ -0,0 +1,94 @@
+//! Short text messages pushed to the stick, over the UDP RPC or ESP-NOW.
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use embassy_time::Instant;
+use serde::{Deserialize, Serialize};
+
+/// Longer texts are cut, a page has to fit an ESP-NOW frame.
+pub const MAX_TEXT_LEN: usize = 160;
+pub const MAX_FROM_LEN: usize = 16;
+/// Messages kept, the oldest goes first.
+pub const INBOX_SIZE: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page {
+    pub from: String,
+    pub text: String,
+}
+
+impl Page {
+    pub fn new(from: &str, text: &str) -> Self {
+        Self {
+            from: truncated(from, MAX_FROM_LEN).into(),
+            text: truncated(text, MAX_TEXT_LEN).into(),
+        }
+    }
+}
+
+/// Cut to at most `max` bytes on a char boundary.
+fn truncated(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub page: Page,
+    pub received: Instant,
+    pub read: bool,
+}
+
+/// Newest first.
+#[derive(Debug, Default)]
+pub struct Inbox {
+    messages: Vec<Message>,
+}
+
+impl Inbox {
+    pub fn push(&mut self, page: Page) {
+        // whatever came in over the wire
+        let page = Page::new(&page.from, &page.text);
+        self.messages.insert(
+            0,
+            Message {
+                page,
+                received: Instant::now(),
+                read: false,
+            },
+        );
+        self.messages.truncate(INBOX_SIZE);
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn unread(&self) -> usize {
+        self.messages.iter().filter(|m| !m.read).count()
+    }
+
+    pub fn mark_read(&mut self, index: usize) {
+        if let Some(message) = self.messages.get_mut(index) {
+            message.read = true;
+        }
+    }
+
+    pub fn dismiss(&mut self, index: usize) -> Option<Message> {
+        (index < self.messages.len()).then(|| self.messages.remove(index))
+    }
+}
@@ -0,0 +1,110 @@
+//! What a stick does: the same firmware runs as a TV remote, a vehicle controller or a vehicle.
+//!
+//! The role is picked at boot with [`select_role`] and decides which tabs the app shows
+//! and which tasks the platform spawns.
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::ToString, vec::Vec};
+#[cfg(feature = "std")]
+use std::{string::ToString, vec::Vec};
+
+use ratatui::{
+    Terminal,
+    prelude::{Backend, Widget},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Padding, Paragraph},
+};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::{
+    events::{Button, Event, JoyC, JoycDirection, Receiver},
+    layout::AppLayout,
+};
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::Display,
+    strum::EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum Role {
+    /// IR TV remote.
+    #[default]
+    Remote,
+    /// Drives a vehicle over ESP-NOW.
+    Controller,
+    /// Receives ESP-NOW drive commands and moves the 8Servos hat.
+    Vehicle,
+}
+
+/// Shows the role menu until one is picked: B or the joystick moves, A or the joystick button selects.
+///
+/// A only selects if it went down while the menu was up, releasing the A held to open it doesn't.
+pub async fn select_role<B: Backend>(
+    terminal: &mut Terminal<B>,
+    receiver: &mut Receiver,
+    current: Option<Role>,
+) -> Result<Role, B::Error> {
+    let roles = Role::iter().collect::<Vec<_>>();
+    let mut selected = current
+        .and_then(|role| roles.iter().position(|r| *r == role))
+        .unwrap_or(0);
+    let mut a_pressed = false;
+
+    loop {
+        terminal.draw(|frame| {
+            let layout = AppLayout::new(frame.area());
+            let buf = frame.buffer_mut();
+
+            Paragraph::new(" role")
+                .style(Style::new().fg(Color::White).bg(Color::Rgb(50, 50, 50)))
+                .render(layout.header, buf);
+
+            let lines = roles
+                .iter()
+                .enumerate()
+                .map(|(i, role)| {
+                    let text = role.to_string();
+                    if i == selected {
+                        Line::styled(text, Style::new().fg(Color::Black).bg(Color::White))
+                    } else {
+                        Line::styled(text, Style::new().fg(Color::Gray))
+                    }
+                })
+                .collect::<Vec<_>>();
+            Paragraph::new(lines)
+                .block(Block::new().padding(Padding::new(2, 2, 1, 0)))
+                .render(layout.main, buf);
+
+            Paragraph::new(" b - next, a - select")
+                .style(Style::new().fg(Color::Gray))
+                .render(layout.footer, buf);
+        })?;
+
+        match receiver.next_message_pure().await {
+            Event::ButtonUp(Button::B) | Event::JoyC(JoyC::Arrow(JoycDirection::Down)) => {
+                selected = (selected + 1) % roles.len();
+            }
+            Event::JoyC(JoyC::Arrow(JoycDirection::Up)) => {
+                selected = (selected + roles.len() - 1) % roles.len();
+            }
+            Event::ButtonDown(Button::A) => a_pressed = true,
+            Event::ButtonUp(Button::A) if !a_pressed => {}
+            Event::ButtonUp(Button::A) | Event::JoyC(JoyC::Button) => {
+                log::info!("role: {}", roles[selected]);
+                return Ok(roles[selected]);
+            }
+            _ => {}
+        }
+    }
+}
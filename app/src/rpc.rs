@@ -0,0 +1,312 @@
+//! UDP RPC protocol shared by the stick, the simulator and the host `cli`.
+//!
+//! Every datagram is a single postcard-encoded [`Request`] or [`Response`]. Apart from
+//! [`Request::Ping`] and pairing, requests only work wrapped in [`Request::Authenticated`],
+//! see [`crate::auth`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use log::Level;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{self, AuthError, Mac, Nonce, Proof, Token},
+    events::{Event, Remote, Stats, Vehicle},
+    logger,
+    ota::{OtaError, OtaFlash, OtaReceiver, OtaState},
+    pager::Page,
+};
+
+pub const RPC_PORT: u16 = 9000;
+pub const MAX_PACKET_SIZE: usize = 1024;
+
+/// Rough budget for log messages in a single [`Response::Logs`],
+/// the rest of the packet is left for framing.
+const LOGS_BUDGET: usize = MAX_PACKET_SIZE - 128;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    Ping,
+    Remote(Remote),
+    Vehicle(Vehicle),
+    /// Log lines with a sequence number greater than `after`.
+    Logs {
+        after: u32,
+    },
+    Stats,
+    /// Starts a firmware update, see [`crate::ota`].
+    OtaBegin {
+        size: u32,
+        sha256: [u8; 32],
+    },
+    OtaChunk {
+        offset: u32,
+        crc32: u32,
+        data: Vec<u8>,
+    },
+    /// Verifies and activates the image, the device reboots after the response.
+    OtaFinish,
+    /// Shows a message, or with `relay` broadcasts it to the sticks around over ESP-NOW.
+    Page {
+        page: Page,
+        relay: bool,
+    },
+    /// Asks to pair, the stick shows a PIN and answers with [`Response::PairChallenge`].
+    PairBegin {
+        client: String,
+    },
+    /// [`auth::proof`] of the PIN on screen, answered with [`Response::Paired`].
+    Pair {
+        proof: Proof,
+    },
+    /// A request from a paired host, see [`Request::authenticated`].
+    Authenticated {
+        token: Token,
+        counter: u64,
+        /// [`auth::hmac`] of `counter` and `request`.
+        mac: Mac,
+        /// The encoded request.
+        request: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Response {
+    Pong {
+        name: String,
+        version: String,
+    },
+    Ok,
+    Logs(Vec<LogLine>),
+    Stats(Stats),
+    Error(String),
+    /// Chunk written, send the one at `next`.
+    OtaAck {
+        next: u32,
+    },
+    OtaError(OtaError),
+    PairChallenge {
+        nonce: Nonce,
+    },
+    /// Wrap requests in [`Request::Authenticated`] with this and the [`auth::session_key`].
+    Paired {
+        token: Token,
+    },
+    AuthError(AuthError),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogLine {
+    pub seq: u32,
+    pub level: Level,
+    pub msg: String,
+}
+
+pub fn encode<'a, T: Serialize>(
+    msg: &T,
+    buf: &'a mut [u8],
+) -> Result<&'a mut [u8], postcard::Error> {
+    postcard::to_slice(msg, buf)
+}
+
+pub fn decode<'a, T: Deserialize<'a>>(buf: &'a [u8]) -> Result<T, postcard::Error> {
+    postcard::from_bytes(buf)
+}
+
+impl Request {
+    /// Wraps the request in a session, `counter` has to be higher than the last one sent.
+    pub fn authenticated(
+        &self,
+        token: Token,
+        key: &auth::Key,
+        counter: u64,
+    ) -> Result<Request, postcard::Error> {
+        let request = postcard::to_allocvec(self)?;
+        Ok(Request::Authenticated {
+            token,
+            counter,
+            mac: auth::hmac(key, counter, &request),
+            request,
+        })
+    }
+}
+
+/// What [`authenticate`] made of a request.
+pub enum Authorization {
+    /// Act on this one.
+    Granted(Request),
+    /// Nothing left to do but send the response and publish the event.
+    Answered(Response, Option<Event>),
+}
+
+/// Answers pairing and unwraps authenticated requests, the rest is refused.
+pub fn authenticate(request: Request) -> Authorization {
+    let refused = |e| Authorization::Answered(Response::AuthError(e), None);
+    match request {
+        Request::Ping => Authorization::Granted(request),
+        Request::PairBegin { client } => match auth::begin_pairing(&client) {
+            Ok((nonce, pin)) => {
+                log::info!("pairing {}", pin.client);
+                Authorization::Answered(
+                    Response::PairChallenge { nonce },
+                    Some(Event::PairingPin(Some(pin))),
+                )
+            }
+            Err(e) => refused(e),
+        },
+        Request::Pair { proof } => {
+            let response = match auth::pair(&proof) {
+                Ok((client, token)) => {
+                    log::info!("paired {}", client);
+                    Response::Paired { token }
+                }
+                Err(e) => {
+                    log::warn!("pairing failed: {}", e);
+                    Response::AuthError(e)
+                }
+            };
+            let done = (!auth::is_pairing()).then_some(Event::PairingPin(None));
+            Authorization::Answered(response, done)
+        }
+        Request::Authenticated {
+            token,
+            counter,
+            mac,
+            request,
+        } => match auth::check(&token, counter, &mac, &request).map(|_| decode(&request)) {
+            Err(e) => refused(e),
+            Ok(Err(_)) => Authorization::Answered(Response::Error("bad request".into()), None),
+            Ok(Ok(
+                Request::PairBegin { .. } | Request::Pair { .. } | Request::Authenticated { .. },
+            )) => Authorization::Answered(Response::Error("not in a session".into()), None),
+            Ok(Ok(request)) => Authorization::Granted(request),
+        },
+        _ => refused(AuthError::Unauthorized),
+    }
+}
+
+/// Transport-agnostic request handler.
+///
+/// Keeps the bits of app state requests need (e.g. the latest [`Stats`]),
+/// feed it every app event with [`RpcHandler::update`].
+pub struct RpcHandler {
+    name: &'static str,
+    stats: Stats,
+    ota: Option<OtaReceiver>,
+}
+
+impl RpcHandler {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            stats: Stats::default(),
+            ota: None,
+        }
+    }
+
+    /// Accepts firmware updates into `flash`, without it OTA requests are refused.
+    pub fn with_ota(mut self, flash: impl OtaFlash + 'static) -> Self {
+        self.ota = Some(OtaReceiver::new(flash));
+        self
+    }
+
+    /// An update was verified and activated, time to reboot.
+    pub fn ota_done(&self) -> bool {
+        self.ota
+            .as_ref()
+            .is_some_and(|ota| ota.state() == OtaState::Done)
+    }
+
+    pub fn update(&mut self, event: &Event) {
+        if let Event::StatsUpdated(stats) = event {
+            self.stats = *stats;
+        }
+    }
+
+    /// Returns the response and an event to publish, if any.
+    pub fn handle(&mut self, request: Request) -> (Response, Option<Event>) {
+        let request = match authenticate(request) {
+            Authorization::Granted(request) => request,
+            Authorization::Answered(response, event) => return (response, event),
+        };
+        match request {
+            Request::Ping => (
+                Response::Pong {
+                    name: self.name.into(),
+                    version: env!("CARGO_PKG_VERSION").into(),
+                },
+                None,
+            ),
+            Request::Remote(remote) => (Response::Ok, Some(Event::Remote(remote))),
+            Request::Vehicle(vehicle) => (Response::Ok, Some(Event::Vehicle(vehicle))),
+            Request::Logs { after } => {
+                let mut budget = LOGS_BUDGET;
+                let lines = logger::log_lines_after(after)
+                    .into_iter()
+                    .take_while(|(_, _, msg)| {
+                        let size = msg.len() + 8;
+                        let fits = size <= budget;
+                        budget = budget.saturating_sub(size);
+                        fits
+                    })
+                    .map(|(seq, level, msg)| LogLine { seq, level, msg })
+                    .collect();
+                (Response::Logs(lines), None)
+            }
+            Request::Stats => (Response::Stats(self.stats), None),
+            Request::OtaBegin { size, sha256 } => {
+                let response = self.ota(|ota| {
+                    ota.begin(size, sha256)?;
+                    log::info!("OTA: receiving {} bytes", size);
+                    Ok(Response::Ok)
+                });
+                (response, None)
+            }
+            Request::OtaChunk {
+                offset,
+                crc32,
+                data,
+            } => {
+                let response = self.ota(|ota| {
+                    let next = ota.chunk(offset, crc32, &data)?;
+                    Ok(Response::OtaAck { next })
+                });
+                (response, None)
+            }
+            Request::OtaFinish => {
+                let response = self.ota(|ota| {
+                    ota.finish()?;
+                    log::info!("OTA: image verified and activated");
+                    Ok(Response::Ok)
+                });
+                (response, None)
+            }
+            Request::Page { page, relay } => {
+                log::info!("page from {}", page.from);
+                let event = if relay {
+                    Event::SendPage(page)
+                } else {
+                    Event::Page(page)
+                };
+                (Response::Ok, Some(event))
+            }
+            // `authenticate` answers these itself
+            Request::PairBegin { .. } | Request::Pair { .. } | Request::Authenticated { .. } => {
+                (Response::Error("not in a session".into()), None)
+            }
+        }
+    }
+
+    fn ota(&mut self, f: impl FnOnce(&mut OtaReceiver) -> Result<Response, OtaError>) -> Response {
+        let Some(ota) = &mut self.ota else {
+            return Response::Error("OTA not supported".into());
+        };
+        f(ota).unwrap_or_else(|e| {
+            log::warn!("OTA: {:?}", e);
+            Response::OtaError(e)
+        })
+    }
+}
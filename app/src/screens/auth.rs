@@ -0,0 +1,130 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::Widget,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Padding, Paragraph},
+};
+
+use super::{Context, Screen, format_secs};
+use crate::{
+    auth,
+    events::{Button, Event, JoyC, JoycDirection},
+    input::Gesture,
+    overlay::Confirm,
+};
+
+/// Paired hosts, the PIN of one pairing is an [`crate::overlay`].
+#[derive(Default)]
+pub struct Auth {
+    selected_client: usize,
+}
+
+impl Auth {
+    fn next_client(&mut self) {
+        let len = auth::clients().len();
+        if len > 0 {
+            self.selected_client = (self.selected_client.min(len - 1) + 1) % len;
+        }
+    }
+
+    fn prev_client(&mut self) {
+        let len = auth::clients().len();
+        if len > 0 {
+            self.selected_client = (self.selected_client.min(len - 1) + len - 1) % len;
+        }
+    }
+
+    /// Asks first, the session ends with [`Event::RevokeClient`] on a yes.
+    fn revoke_selected_client(&mut self, ctx: &mut Context) {
+        let clients = auth::clients();
+        if let Some(client) = clients.get(self.selected_client.min(clients.len().saturating_sub(1)))
+        {
+            ctx.publish(Event::Confirm(Confirm::new(
+                format!("revoke {}?", client.name),
+                Event::RevokeClient(client.name.clone()),
+            )));
+        }
+    }
+}
+
+impl Screen for Auth {
+    fn title(&self) -> &'static str {
+        "auth"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        let clients = auth::clients();
+        if clients.is_empty() {
+            Paragraph::new("no paired hosts")
+                .centered()
+                .style(Style::new().fg(Color::DarkGray))
+                .block(Block::new().padding(Padding::top(1)))
+                .render(area, buf);
+            return;
+        }
+
+        let selected = self.selected_client.min(clients.len() - 1);
+        // keep the selected client on screen
+        let skip = selected.saturating_sub((area.height as usize).saturating_sub(1));
+
+        let lines = clients
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, client)| {
+                let text = format!(
+                    "{:<16} paired {:>3} seen {:>3}",
+                    client.name,
+                    format_secs(client.paired.elapsed().as_secs() as u32),
+                    format_secs(client.last_seen.elapsed().as_secs() as u32)
+                );
+                let mut style = Style::new().fg(Color::White);
+                if i == selected {
+                    style = style.bg(Color::Rgb(50, 50, 50));
+                }
+                Line::styled(text, style)
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines)
+            .block(Block::new().padding(Padding::left(1)))
+            .render(area, buf);
+    }
+
+    fn update(&mut self, event: &Event) {
+        if let Event::RevokeClient(name) = event
+            && auth::revoke(name)
+        {
+            log::info!("revoked {}", name);
+            self.selected_client = self
+                .selected_client
+                .min(auth::clients().len().saturating_sub(1));
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event, ctx: &mut Context) -> bool {
+        match event {
+            Event::JoyC(JoyC::Button) | Event::Gesture(Gesture::Click(Button::A)) => {
+                self.revoke_selected_client(ctx)
+            }
+            Event::JoyC(JoyC::Arrow(JoycDirection::Up)) => self.prev_client(),
+            Event::JoyC(JoyC::Arrow(JoycDirection::Down))
+            | Event::Gesture(Gesture::Click(Button::B) | Gesture::Repeat(Button::B)) => {
+                self.next_client()
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn footer(&self, _ctx: &Context) -> Option<String> {
+        (!auth::clients().is_empty()).then(|| " a - revoke, b - next".into())
+    }
+}
@@ -0,0 +1,47 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::Widget,
+    widgets::{Block, Padding, Paragraph},
+};
+
+use super::{Context, Screen};
+use crate::{
+    events::{self, Event, JoyC},
+    role::Role,
+};
+
+/// Drives a vehicle with the joystick.
+pub struct Controller;
+
+impl Screen for Controller {
+    fn title(&self) -> &'static str {
+        "ctrl"
+    }
+
+    fn enabled(&self, role: Role) -> bool {
+        role == Role::Controller
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        // TODO
+        Paragraph::new("Vehicle Controller")
+            .centered()
+            .block(Block::new().padding(Padding::top(1)))
+            .render(area, buf);
+    }
+
+    fn handle_event(&mut self, event: &Event, ctx: &mut Context) -> bool {
+        let Event::JoyC(JoyC::Pos((x, y))) = event else {
+            return false;
+        };
+        let x = *x as i16;
+        let y = *y as i16;
+
+        let left = (y + x).clamp(-100, 100) as i8;
+        let right = (y - x).clamp(-100, 100) as i8;
+
+        ctx.publish(Event::Controller(events::Controller::Move(left, right)));
+        true
+    }
+}
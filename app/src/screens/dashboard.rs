@@ -0,0 +1,65 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    prelude::Widget,
+    style::{Color, Style},
+    widgets::{Block, Padding, Paragraph},
+};
+
+use super::{Context, Screen, draw_key_values};
+use crate::events::{self, Event};
+
+/// Fields picked out of a JSON endpoint.
+#[derive(Default)]
+pub struct Dashboard {
+    dashboard: events::Dashboard,
+}
+
+impl Screen for Dashboard {
+    fn title(&self) -> &'static str {
+        "dash"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        let dashboard = &self.dashboard;
+        if dashboard.rows.is_empty() {
+            Paragraph::new(dashboard.error.as_deref().unwrap_or("waiting for data"))
+                .centered()
+                .style(Style::new().fg(Color::DarkGray))
+                .block(Block::new().padding(Padding::top(1)))
+                .render(area, buf);
+            return;
+        }
+
+        let [rows_area, error_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        let rows = dashboard
+            .rows
+            .iter()
+            .map(|(label, value)| (label.as_str(), value.clone()))
+            .collect::<Vec<_>>();
+        draw_key_values(rows_area, buf, &rows);
+
+        // the rows are from the last poll that worked
+        if let Some(error) = &dashboard.error {
+            Paragraph::new(format!(" {}", error))
+                .style(Style::new().fg(Color::Red))
+                .render(error_area, buf);
+        }
+    }
+
+    fn update(&mut self, event: &Event) {
+        if let Event::Dashboard(dashboard) = event {
+            if dashboard.error.is_some() && dashboard.rows.is_empty() {
+                self.dashboard.error = dashboard.error.clone();
+            } else {
+                self.dashboard = dashboard.clone();
+            }
+        }
+    }
+}
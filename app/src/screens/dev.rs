@@ -0,0 +1,49 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use log::Level;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::Widget,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Paragraph, Wrap},
+};
+
+use super::{Context, Screen};
+use crate::logger;
+
+/// The latest log lines.
+pub struct Dev;
+
+impl Screen for Dev {
+    fn title(&self) -> &'static str {
+        "dev"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        let log_lines = logger::latest_log_lines(area.height as usize);
+        let log_lines = log_lines
+            .iter()
+            .rev()
+            .map(|(level, msg)| {
+                let color = match level {
+                    Level::Error => Color::Red,
+                    Level::Warn => Color::Yellow,
+                    Level::Info => Color::White,
+                    Level::Debug => Color::DarkGray,
+                    Level::Trace => Color::Magenta,
+                };
+
+                Line::styled(msg, Style::new().fg(color))
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(log_lines)
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,153 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    prelude::Widget,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Padding, Paragraph, Wrap},
+};
+
+use super::{Context, Screen, format_secs};
+use crate::{
+    events::{Button, Event, JoyC, JoycDirection},
+    input::Gesture,
+    pager,
+};
+
+/// Pages received, the newest is an [`crate::overlay`] until a button is pressed.
+#[derive(Default)]
+pub struct Inbox {
+    inbox: pager::Inbox,
+    selected_message: usize,
+}
+
+impl Inbox {
+    fn select_message(&mut self, index: usize) {
+        self.selected_message = index;
+        self.inbox.mark_read(index);
+    }
+
+    fn next_message(&mut self) {
+        if !self.inbox.is_empty() {
+            self.select_message((self.selected_message + 1) % self.inbox.len());
+        }
+    }
+
+    fn prev_message(&mut self) {
+        if !self.inbox.is_empty() {
+            let len = self.inbox.len();
+            self.select_message((self.selected_message + len - 1) % len);
+        }
+    }
+
+    fn dismiss_message(&mut self) {
+        if self.inbox.dismiss(self.selected_message).is_some() {
+            self.select_message(
+                self.selected_message
+                    .min(self.inbox.len().saturating_sub(1)),
+            );
+        }
+    }
+}
+
+impl Screen for Inbox {
+    fn title(&self) -> &'static str {
+        "inbox"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        if self.inbox.is_empty() {
+            Paragraph::new("no messages")
+                .centered()
+                .style(Style::new().fg(Color::DarkGray))
+                .block(Block::new().padding(Padding::top(1)))
+                .render(area, buf);
+            return;
+        }
+
+        let [list_area, text_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(3)]).areas(area);
+
+        // keep the selected message on screen
+        let skip = self
+            .selected_message
+            .saturating_sub((list_area.height as usize).saturating_sub(1));
+
+        let lines = self
+            .inbox
+            .messages()
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, message)| {
+                let text = format!(
+                    "{} {:<8.8} {:>3} {}",
+                    if message.read { ' ' } else { '*' },
+                    message.page.from,
+                    format_secs(message.received.elapsed().as_secs() as u32),
+                    message.page.text
+                );
+                let mut style = Style::new().fg(if message.read {
+                    Color::DarkGray
+                } else {
+                    Color::White
+                });
+                if i == self.selected_message {
+                    style = style.bg(Color::Rgb(50, 50, 50));
+                }
+                Line::styled(text, style)
+            })
+            .collect::<Vec<_>>();
+        Paragraph::new(lines).render(list_area, buf);
+
+        if let Some(message) = self.inbox.messages().get(self.selected_message) {
+            Paragraph::new(message.page.text.as_str())
+                .wrap(Wrap { trim: true })
+                .block(Block::new().padding(Padding::horizontal(1)))
+                .render(text_area, buf);
+        }
+    }
+
+    fn update(&mut self, event: &Event) {
+        match event {
+            Event::Page(page) => {
+                log::info!("page from {}: {}", page.from, page.text);
+                self.inbox.push(page.clone());
+                // keep the same message selected
+                if self.inbox.len() > 1 {
+                    self.selected_message = (self.selected_message + 1).min(self.inbox.len() - 1);
+                }
+            }
+            Event::PageRead => self.inbox.mark_read(0),
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event, _ctx: &mut Context) -> bool {
+        match event {
+            Event::JoyC(JoyC::Button) | Event::Gesture(Gesture::Click(Button::A)) => {
+                self.dismiss_message()
+            }
+            Event::JoyC(JoyC::Arrow(JoycDirection::Up)) => self.prev_message(),
+            Event::JoyC(JoyC::Arrow(JoycDirection::Down))
+            | Event::Gesture(Gesture::Click(Button::B) | Gesture::Repeat(Button::B)) => {
+                self.next_message()
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn footer(&self, _ctx: &Context) -> Option<String> {
+        (!self.inbox.is_empty()).then(|| " a - dismiss, b - next".into())
+    }
+
+    fn selected(&mut self) {
+        self.inbox.mark_read(self.selected_message);
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use std::string::{String, ToString};
+
+use ratatui::{buffer::Buffer, layout::Rect};
+#[cfg(feature = "discovery")]
+use ratatui::{
+    layout::{Constraint, Layout},
+    prelude::Widget,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Padding, Paragraph},
+};
+
+use super::{Context, Screen, draw_key_values, format_secs};
+use crate::{
+    backlight,
+    battery::PowerSource,
+    events::{Button, Event, Stats},
+    input::Gesture,
+};
+
+pub struct Info {
+    stats: Stats,
+    /// Last level asked for, in percent.
+    brightness: u8,
+}
+
+impl Default for Info {
+    fn default() -> Self {
+        Self {
+            stats: Stats::default(),
+            brightness: backlight::DEFAULT,
+        }
+    }
+}
+
+impl Screen for Info {
+    fn title(&self) -> &'static str {
+        "info"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, ctx: &Context) {
+        let info = [
+            ("battery", battery(&self.stats)),
+            (
+                "hat",
+                ctx.hat
+                    .map(|h| format!("{:?}", h))
+                    .unwrap_or("-".to_string()),
+            ),
+            (
+                "heap",
+                format!(
+                    "{}/{}",
+                    self.stats.heap_used / 1024,
+                    (self.stats.heap_used + self.stats.heap_free) / 1024
+                ),
+            ),
+            ("backlight", format!("{}%", self.brightness)),
+        ];
+
+        #[cfg(feature = "discovery")]
+        let area = {
+            let [info_area, peers_area] = Layout::vertical([
+                Constraint::Length(info.len() as u16 + 2),
+                Constraint::Fill(1),
+            ])
+            .areas(area);
+            draw_peers(peers_area, buf);
+            info_area
+        };
+        draw_key_values(area, buf, &info);
+    }
+
+    fn update(&mut self, event: &Event) {
+        match event {
+            Event::StatsUpdated(stats) => {
+                self.stats = *stats;
+            }
+            Event::SetBrightness(percent) => {
+                self.brightness = *percent;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event, ctx: &mut Context) -> bool {
+        if !matches!(event, Event::Gesture(Gesture::Click(Button::A))) {
+            return false;
+        }
+        ctx.publish(Event::SetBrightness(backlight::next_level(self.brightness)));
+        true
+    }
+
+    fn footer(&self, _ctx: &Context) -> Option<String> {
+        Some(" a - backlight".into())
+    }
+}
+
+/// `87% 3.92V`, then what it's running on or how long it lasts.
+fn battery(stats: &Stats) -> String {
+    let level = format!(
+        "{}% {}.{:02}V",
+        stats.battery_level,
+        stats.battery_mv / 1000,
+        stats.battery_mv % 1000 / 10
+    );
+    match (stats.power, stats.minutes_left) {
+        (PowerSource::Battery, Some(minutes)) => {
+            format!("{} ~{}", level, format_secs(minutes as u32 * 60))
+        }
+        (PowerSource::Battery, None) => level,
+        (power, _) => format!("{} {}", level, <&str>::from(power)),
+    }
+}
+
+/// Sticks around on the LAN, one per line.
+#[cfg(feature = "discovery")]
+fn draw_peers(area: Rect, buf: &mut Buffer) {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+    #[cfg(feature = "std")]
+    use std::vec::Vec;
+
+    let peers = crate::discovery::peers();
+    if peers.is_empty() {
+        Paragraph::new("no peers")
+            .centered()
+            .style(Style::new().fg(Color::DarkGray))
+            .render(area, buf);
+        return;
+    }
+
+    let lines = peers
+        .iter()
+        .map(|peer| {
+            let announce = &peer.announce;
+            Line::styled(
+                format!(
+                    "{:<12} {:<10} {:>3}% {}",
+                    announce.name,
+                    announce.role.to_string(),
+                    announce.battery,
+                    announce.capabilities
+                ),
+                Style::new().fg(Color::White),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Paragraph::new(lines)
+        .block(Block::new().padding(Padding::left(1)))
+        .render(area, buf);
+}
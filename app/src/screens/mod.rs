@@ -0,0 +1,177 @@
+//! The tabs of the app, one [`Screen`] each.
+//!
+//! [`registry`] lists them in tab order, a new tab is a module here and a line there.
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{boxed::Box, string::String, vec::Vec};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Margin, Rect},
+    prelude::Widget,
+    style::{Color, Style},
+    widgets::Paragraph,
+};
+
+use crate::{
+    events::{Event, StickHat},
+    input::Thresholds,
+    role::Role,
+    sleep::Snapshot,
+};
+
+#[cfg(feature = "auth")]
+mod auth;
+#[cfg(feature = "controller")]
+mod controller;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod dev;
+#[cfg(feature = "pager")]
+mod inbox;
+mod info;
+#[cfg(feature = "monitor")]
+mod monitor;
+#[cfg(feature = "net")]
+mod net;
+#[cfg(feature = "scan")]
+mod scan;
+#[cfg(feature = "tv")]
+mod tv;
+#[cfg(feature = "vehicle")]
+mod vehicle;
+
+pub trait Screen {
+    /// Shown in the header, keep it short.
+    fn title(&self) -> &'static str;
+
+    /// Role-specific screens only show up in their role.
+    fn enabled(&self, _role: Role) -> bool {
+        true
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, ctx: &Context);
+
+    /// Every screen sees every event, showing or not.
+    fn update(&mut self, _event: &Event) {}
+
+    /// An [`Event::Gesture`] or [`Event::JoyC`] while the screen is showing, `true` if it did something.
+    fn handle_event(&mut self, _event: &Event, _ctx: &mut Context) -> bool {
+        false
+    }
+
+    /// What the buttons do, e.g. ` a - scan, b - next`.
+    fn footer(&self, _ctx: &Context) -> Option<String> {
+        None
+    }
+
+    /// Switched to this screen.
+    fn selected(&mut self) {}
+
+    /// Going to deep sleep, anything worth coming back to goes into `snapshot`.
+    fn save(&self, _snapshot: &mut Snapshot) {}
+
+    /// Back from deep sleep, after the saved tab was selected again.
+    fn restore(&mut self, _snapshot: &Snapshot) {}
+}
+
+/// What screens get from the app besides events.
+pub struct Context {
+    pub role: Role,
+    pub hat: Option<StickHat>,
+    /// The screen was used since it was selected.
+    pub tab_touched: bool,
+    pub thresholds: Thresholds,
+    /// How long B has been held down so far, 0 when it is up.
+    pub b_held_ms: u64,
+    pub c_held_ms: u64,
+    outbox: Vec<Event>,
+}
+
+impl Context {
+    pub fn new(
+        role: Role,
+        hat: Option<StickHat>,
+        tab_touched: bool,
+        thresholds: Thresholds,
+        b_held_ms: u64,
+        c_held_ms: u64,
+    ) -> Self {
+        Self {
+            role,
+            hat,
+            tab_touched,
+            thresholds,
+            b_held_ms,
+            c_held_ms,
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Goes out on the event bus once the screen is done with the event.
+    pub fn publish(&mut self, event: Event) {
+        self.outbox.push(event);
+    }
+
+    pub fn take_outbox(&mut self) -> Vec<Event> {
+        core::mem::take(&mut self.outbox)
+    }
+}
+
+/// Every screen built in, in tab order.
+pub fn registry() -> Vec<Box<dyn Screen>> {
+    vec![
+        Box::new(info::Info::default()),
+        #[cfg(feature = "controller")]
+        Box::new(controller::Controller),
+        #[cfg(feature = "vehicle")]
+        Box::new(vehicle::Vehicle),
+        #[cfg(feature = "tv")]
+        Box::new(tv::Tv::new()),
+        #[cfg(feature = "net")]
+        Box::new(net::Net::default()),
+        #[cfg(feature = "scan")]
+        Box::new(scan::Scan::default()),
+        #[cfg(feature = "monitor")]
+        Box::new(monitor::Monitor::default()),
+        #[cfg(feature = "pager")]
+        Box::new(inbox::Inbox::default()),
+        #[cfg(feature = "dashboard")]
+        Box::new(dashboard::Dashboard::default()),
+        // after the inbox, pairing goes over a page
+        #[cfg(feature = "auth")]
+        Box::new(auth::Auth::default()),
+        Box::new(dev::Dev),
+    ]
+}
+
+/// One `title  value` row per entry.
+fn draw_key_values(area: Rect, buf: &mut Buffer, info: &[(&str, String)]) {
+    let horizontal = Layout::horizontal([Constraint::Max(10), Constraint::Fill(1)]);
+    let vertical = Layout::vertical((0..info.len()).map(|_| Constraint::Length(1)));
+
+    let rows = vertical.split(area.inner(Margin::new(1, 1)));
+
+    let info_style = Style::new().fg(Color::DarkGray);
+
+    for (row, (title, value)) in rows.iter().cloned().zip(info) {
+        let [title_area, value_area] = horizontal.areas(row);
+
+        Paragraph::new(*title)
+            .style(info_style)
+            .render(title_area, buf);
+        Paragraph::new(value.as_str()).render(value_area, buf);
+    }
+}
+
+/// `42s`, `5m`, `3h`, `2d`
+fn format_secs(secs: u32) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86_400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Margin, Rect},
+    prelude::Widget,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Padding, Paragraph, Sparkline},
+};
+
+use super::{Context, Screen};
+use crate::events::{Event, PacketStats};
+
+/// What the radio hears in promiscuous mode.
+#[derive(Default)]
+pub struct Monitor {
+    packet_stats: PacketStats,
+    /// Frames per second, oldest first.
+    frame_rates: Vec<u64>,
+}
+
+impl Screen for Monitor {
+    fn title(&self) -> &'static str {
+        "mon"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        if self.frame_rates.is_empty() {
+            Paragraph::new("waiting for frames")
+                .centered()
+                .style(Style::new().fg(Color::DarkGray))
+                .block(Block::new().padding(Padding::top(1)))
+                .render(area, buf);
+            return;
+        }
+
+        let stats = &self.packet_stats;
+        let [rate_area, graph_area, channels_area, talkers_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(area.inner(Margin::new(1, 0)));
+
+        Line::from(vec![
+            Span::raw(format!("{}/s", stats.frames)),
+            Span::styled(
+                format!(" esp-now {}/s", stats.esp_now),
+                Style::new().fg(Color::Cyan),
+            ),
+        ])
+        .render(rate_area, buf);
+
+        let shown = self
+            .frame_rates
+            .len()
+            .saturating_sub(graph_area.width as usize);
+        Sparkline::default()
+            .data(&self.frame_rates[shown..])
+            .style(Style::new().fg(Color::Green))
+            .render(graph_area, buf);
+
+        let channels = stats
+            .channels
+            .iter()
+            .enumerate()
+            .filter(|(_, frames)| **frames > 0)
+            .map(|(i, frames)| format!("{}:{}", i + 1, frames))
+            .collect::<Vec<_>>()
+            .join(" ");
+        Paragraph::new(format!("ch {}", channels))
+            .style(Style::new().fg(Color::Gray))
+            .render(channels_area, buf);
+
+        let lines = stats
+            .talkers
+            .iter()
+            .map(|talker| {
+                let m = talker.mac;
+                let mut spans = vec![Span::raw(format!(
+                    "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x} {:>5} {:>4}",
+                    m[0], m[1], m[2], m[3], m[4], m[5], talker.frames, talker.rssi
+                ))];
+                if talker.esp_now > 0 {
+                    spans.push(Span::styled(" now", Style::new().fg(Color::Cyan)));
+                }
+                Line::from(spans)
+            })
+            .collect::<Vec<_>>();
+        Paragraph::new(lines).render(talkers_area, buf);
+    }
+
+    fn update(&mut self, event: &Event) {
+        if let Event::PacketStats(stats) = event {
+            // a bit more than a screen width of history
+            if self.frame_rates.len() == 64 {
+                self.frame_rates.remove(0);
+            }
+            self.frame_rates.push(stats.frames as u64);
+            self.packet_stats = stats.clone();
+        }
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    prelude::Widget,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Padding, Paragraph},
+};
+
+use super::{Context, Screen, draw_key_values, format_secs};
+use crate::{
+    events::{Button, DhcpLease, Event, JoyC, JoycDirection, NetMode, NetStatus},
+    input::Gesture,
+};
+
+/// The link, and the clients leasing from us in AP mode.
+#[derive(Default)]
+pub struct Net {
+    status: NetStatus,
+    leases: Vec<DhcpLease>,
+    selected_lease: usize,
+}
+
+impl Net {
+    fn next_lease(&mut self) {
+        if !self.leases.is_empty() {
+            self.selected_lease = (self.selected_lease + 1) % self.leases.len();
+        }
+    }
+
+    fn prev_lease(&mut self) {
+        if !self.leases.is_empty() {
+            let len = self.leases.len();
+            self.selected_lease = (self.selected_lease + len - 1) % len;
+        }
+    }
+
+    fn kick_selected_lease(&self, ctx: &mut Context) {
+        if let Some(lease) = self.leases.get(self.selected_lease) {
+            log::info!("kicking {}", lease.ip);
+            ctx.publish(Event::KickClient(lease.mac));
+        }
+    }
+}
+
+impl Screen for Net {
+    fn title(&self) -> &'static str {
+        "net"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        let status = &self.status;
+        let or_dash = |value: Option<String>| value.unwrap_or("-".to_string());
+        let info = [
+            (
+                "mode",
+                format!(
+                    "{} {}",
+                    status.mode,
+                    status.ssid.as_deref().unwrap_or_default()
+                ),
+            ),
+            ("ip", or_dash(status.ip.map(|ip| ip.to_string()))),
+            ("gateway", or_dash(status.gateway.map(|ip| ip.to_string()))),
+            (
+                "rssi",
+                match (status.rssi, status.connected_for) {
+                    (Some(rssi), Some(up)) => format!("{} dBm, up {}", rssi, format_secs(up)),
+                    (Some(rssi), None) => format!("{} dBm", rssi),
+                    _ => "-".to_string(),
+                },
+            ),
+            ("reconnect", status.reconnects.to_string()),
+            ("udp", format!("rx {} tx {}", status.udp_rx, status.udp_tx)),
+        ];
+
+        let [status_area, leases_area] = Layout::vertical([
+            Constraint::Length(info.len() as u16 + 2),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        draw_key_values(status_area, buf, &info);
+
+        if self.leases.is_empty() {
+            if matches!(status.mode, NetMode::Ap | NetMode::ApSta) {
+                Paragraph::new("no clients")
+                    .centered()
+                    .style(Style::new().fg(Color::DarkGray))
+                    .render(leases_area, buf);
+            }
+            return;
+        }
+        let area = leases_area;
+
+        // keep the selected lease on screen
+        let skip = self
+            .selected_lease
+            .saturating_sub((area.height as usize).saturating_sub(1));
+
+        let lines = self
+            .leases
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, lease)| {
+                let mac = lease.mac;
+                let expires = if lease.is_stale() {
+                    "expired".to_string()
+                } else {
+                    format!("exp {}", format_secs(lease.expires_in))
+                };
+                let text = format!(
+                    "{:<15} {:02x}{:02x}{:02x} {:<7} {} ago",
+                    lease.ip,
+                    mac[3],
+                    mac[4],
+                    mac[5],
+                    expires,
+                    format_secs(lease.last_seen)
+                );
+
+                let mut style = Style::new().fg(if lease.is_stale() {
+                    Color::DarkGray
+                } else {
+                    Color::White
+                });
+                if i == self.selected_lease {
+                    style = style.bg(Color::Rgb(50, 50, 50));
+                }
+                Line::styled(text, style)
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines)
+            .block(Block::new().padding(Padding::left(1)))
+            .render(area, buf);
+    }
+
+    fn update(&mut self, event: &Event) {
+        match event {
+            Event::NetStatus(status) => {
+                self.status = status.clone();
+            }
+            Event::DhcpLeases(leases) => {
+                self.selected_lease = self.selected_lease.min(leases.len().saturating_sub(1));
+                self.leases = leases.clone();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event, ctx: &mut Context) -> bool {
+        match event {
+            Event::JoyC(JoyC::Button) | Event::Gesture(Gesture::Click(Button::A)) => {
+                self.kick_selected_lease(ctx)
+            }
+            Event::JoyC(JoyC::Arrow(JoycDirection::Up)) => self.prev_lease(),
+            Event::JoyC(JoyC::Arrow(JoycDirection::Down))
+            | Event::Gesture(Gesture::Click(Button::B) | Gesture::Repeat(Button::B)) => {
+                self.next_lease()
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn footer(&self, _ctx: &Context) -> Option<String> {
+        (!self.leases.is_empty()).then(|| " a - kick, b - next".into())
+    }
+}
@@ -0,0 +1,237 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    prelude::Widget,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Bar, BarChart, BarGroup, Block, Padding, Paragraph},
+};
+
+use super::{Context, Screen};
+use crate::{
+    events::{AccessPoint, Button, Event, JoyC, JoycDirection},
+    input::Gesture,
+};
+
+/// Access points around, as a list or per channel.
+#[derive(Default)]
+pub struct Scan {
+    /// Strongest first.
+    access_points: Vec<AccessPoint>,
+    selected_ap: usize,
+    scanning: bool,
+    show_channels: bool,
+}
+
+impl Scan {
+    fn draw_access_points(&self, area: Rect, buf: &mut Buffer) {
+        let [list_area, detail_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+
+        // keep the selected AP on screen
+        let skip = self
+            .selected_ap
+            .saturating_sub((list_area.height as usize).saturating_sub(1));
+
+        let lines = self
+            .access_points
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, ap)| {
+                let rssi_color = match ap.rssi {
+                    -60.. => Color::Green,
+                    -75.. => Color::Yellow,
+                    _ => Color::Red,
+                };
+                let (ssid, ssid_color) = if ap.ssid.is_empty() {
+                    ("<hidden>", Color::DarkGray)
+                } else {
+                    (ap.ssid.as_str(), Color::White)
+                };
+                let mut line = Line::from(vec![
+                    Span::styled(format!("{:>4} ", ap.rssi), Style::new().fg(rssi_color)),
+                    Span::styled(
+                        format!("{:>2} {:<6} ", ap.channel, ap.auth.to_string()),
+                        Style::new().fg(Color::Gray),
+                    ),
+                    Span::styled(ssid, Style::new().fg(ssid_color)),
+                ]);
+                if i == self.selected_ap {
+                    line = line.style(Style::new().bg(Color::Rgb(50, 50, 50)));
+                }
+                line
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines)
+            .block(Block::new().padding(Padding::left(1)))
+            .render(list_area, buf);
+
+        if let Some(ap) = self.access_points.get(self.selected_ap) {
+            let b = ap.bssid;
+            let detail = format!(
+                " {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x} {}/{}",
+                b[0],
+                b[1],
+                b[2],
+                b[3],
+                b[4],
+                b[5],
+                self.selected_ap + 1,
+                self.access_points.len()
+            );
+            Paragraph::new(detail)
+                .style(Style::new().fg(Color::DarkGray))
+                .render(detail_area, buf);
+        }
+    }
+
+    /// One bar per channel: the height is how much the APs' bands overlap it, the number is
+    /// how many APs sit on it.
+    fn draw_channels(&self, area: Rect, buf: &mut Buffer) {
+        // 14 is Japan only, show it when something's there
+        let last = self
+            .access_points
+            .iter()
+            .map(|ap| ap.channel)
+            .max()
+            .unwrap_or_default()
+            .clamp(13, 14);
+
+        let bars = (1..=last)
+            .map(|channel| {
+                let on_channel = self
+                    .access_points
+                    .iter()
+                    .filter(|ap| ap.channel == channel)
+                    .count();
+                let load = self
+                    .access_points
+                    .iter()
+                    .map(|ap| ap.overlap(channel) as u64)
+                    .sum::<u64>();
+                // in quarters, 4 is one AP to itself
+                let color = match load {
+                    0..=4 => Color::Green,
+                    5..=12 => Color::Yellow,
+                    _ => Color::Red,
+                };
+                Bar::default()
+                    .value(load)
+                    .text_value(if on_channel > 0 {
+                        on_channel.to_string()
+                    } else {
+                        String::new()
+                    })
+                    .label(Line::from(channel.to_string()))
+                    .style(Style::new().fg(color))
+                    .value_style(Style::new().fg(Color::Black).bg(color))
+            })
+            .collect::<Vec<_>>();
+
+        BarChart::default()
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(2)
+            .bar_gap(1)
+            .label_style(Style::new().fg(Color::Gray))
+            .render(area, buf);
+    }
+
+    fn next_ap(&mut self) {
+        if !self.access_points.is_empty() {
+            self.selected_ap = (self.selected_ap + 1) % self.access_points.len();
+        }
+    }
+
+    fn prev_ap(&mut self) {
+        if !self.access_points.is_empty() {
+            let len = self.access_points.len();
+            self.selected_ap = (self.selected_ap + len - 1) % len;
+        }
+    }
+
+    fn request_scan(&mut self, ctx: &mut Context) {
+        log::info!("scanning for access points");
+        self.scanning = true;
+        ctx.publish(Event::Scan);
+    }
+}
+
+impl Screen for Scan {
+    fn title(&self) -> &'static str {
+        "scan"
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        if self.access_points.is_empty() {
+            Paragraph::new(if self.scanning {
+                "scanning..."
+            } else {
+                "no access points"
+            })
+            .centered()
+            .style(Style::new().fg(Color::DarkGray))
+            .block(Block::new().padding(Padding::top(1)))
+            .render(area, buf);
+            return;
+        }
+
+        if self.show_channels {
+            self.draw_channels(area, buf);
+        } else {
+            self.draw_access_points(area, buf);
+        }
+    }
+
+    fn update(&mut self, event: &Event) {
+        if let Event::ScanResults(access_points) = event {
+            let mut access_points = access_points.clone();
+            access_points.sort_by_key(|ap| core::cmp::Reverse(ap.rssi));
+            self.selected_ap = self.selected_ap.min(access_points.len().saturating_sub(1));
+            self.access_points = access_points;
+            self.scanning = false;
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event, ctx: &mut Context) -> bool {
+        match event {
+            Event::JoyC(JoyC::Button) | Event::Gesture(Gesture::Click(Button::A)) => {
+                self.request_scan(ctx)
+            }
+            Event::JoyC(JoyC::Arrow(JoycDirection::Up)) => self.prev_ap(),
+            Event::JoyC(JoyC::Arrow(JoycDirection::Down))
+            | Event::Gesture(Gesture::Click(Button::B) | Gesture::Repeat(Button::B)) => {
+                self.next_ap()
+            }
+            Event::JoyC(JoyC::Arrow(JoycDirection::Left | JoycDirection::Right))
+            | Event::Gesture(Gesture::LongPress(Button::B)) => {
+                self.show_channels = !self.show_channels;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn footer(&self, _ctx: &Context) -> Option<String> {
+        let info = if self.access_points.is_empty() {
+            " a - scan"
+        } else if self.show_channels {
+            " a - scan, hold b - list"
+        } else {
+            " a - scan, b - next, hold b - channels"
+        };
+        Some(info.into())
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::string::String;
+
+use ratatui::{buffer::Buffer, layout::Rect, prelude::StatefulWidget};
+
+use super::{Context, Screen};
+use crate::{
+    events::{Button, Event, JoyC, JoycDirection, Remote, StickHat},
+    input::Gesture,
+    remote::{TVRemote, TVState},
+    role::Role,
+    sleep::Snapshot,
+};
+
+pub struct Tv {
+    tv: TVState,
+}
+
+impl Tv {
+    pub fn new() -> Self {
+        Self { tv: TVState::new() }
+    }
+}
+
+impl Screen for Tv {
+    fn title(&self) -> &'static str {
+        "tv"
+    }
+
+    fn enabled(&self, role: Role) -> bool {
+        role == Role::Remote
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        TVRemote::new().render(area, buf, &mut self.tv.clone());
+    }
+
+    fn handle_event(&mut self, event: &Event, ctx: &mut Context) -> bool {
+        match event {
+            // held, like a real remote
+            Event::JoyC(JoyC::Button)
+            | Event::Gesture(
+                Gesture::Click(Button::A)
+                | Gesture::LongPress(Button::A)
+                | Gesture::Repeat(Button::A),
+            ) => {
+                ctx.publish(Event::Remote(self.tv.current_btn));
+            }
+            Event::JoyC(JoyC::Arrow(dir)) => match dir {
+                JoycDirection::Up => {
+                    self.tv.prev_row();
+                }
+                JoycDirection::Right => {
+                    self.tv.next_btn();
+                }
+                JoycDirection::Down => {
+                    self.tv.next_row();
+                }
+                JoycDirection::Left => {
+                    self.tv.prev_btn();
+                }
+                JoycDirection::Center => return false,
+            },
+            Event::Gesture(Gesture::Click(Button::B)) => {
+                self.tv.next_btn();
+            }
+            Event::Gesture(Gesture::LongPress(Button::B) | Gesture::Repeat(Button::B)) => {
+                self.tv.next_row();
+            }
+            // a click on C once the remote is in use
+            Event::Gesture(Gesture::Click(Button::C)) => {
+                self.tv.prev_btn();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn footer(&self, ctx: &Context) -> Option<String> {
+        if matches!(ctx.hat, Some(StickHat::MiniJoyC)) {
+            return None;
+        }
+
+        let long_press = ctx.thresholds.long_press.as_millis();
+        let c_mode = if ctx.tab_touched && ctx.c_held_ms < long_press {
+            "c - prev btn"
+        } else {
+            "c - next tab"
+        };
+        let b_mode = if ctx.b_held_ms >= long_press {
+            "b - next row"
+        } else {
+            "b - next btn"
+        };
+
+        Some(format!(" {}, {}", c_mode, b_mode))
+    }
+
+    fn selected(&mut self) {
+        self.tv.current_btn = Remote::OnOff;
+    }
+
+    fn save(&self, snapshot: &mut Snapshot) {
+        snapshot.remote = Some(self.tv.current_btn);
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) {
+        if let Some(remote) = snapshot.remote {
+            self.tv.current_btn = remote;
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::Widget,
+    widgets::{Block, Padding, Paragraph},
+};
+
+use super::{Context, Screen};
+use crate::role::Role;
+
+pub struct Vehicle;
+
+impl Screen for Vehicle {
+    fn title(&self) -> &'static str {
+        "vehicle"
+    }
+
+    fn enabled(&self, role: Role) -> bool {
+        role == Role::Vehicle
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, _ctx: &Context) {
+        // TODO
+        Paragraph::new("Vehicle")
+            .centered()
+            .block(Block::new().padding(Padding::top(1)))
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,63 @@
+//! Settings that survive a reboot, the platform decides where the record lives.
+//!
+//! A record is a magic, the payload length, a CRC-32 of the payload and the
+//! postcard-encoded [`Settings`]. Anything that doesn't check out (erased flash, an older
+//! layout) reads as `None` and the defaults apply.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{ota::crc32, role::Role, sleep::Snapshot};
+
+/// Bump when [`Settings`] changes shape.
+const MAGIC: [u8; 4] = *b"STK2";
+const HEADER_SIZE: usize = 10;
+/// Upper bound of a record, platforms can reserve this much.
+pub const MAX_RECORD_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// `None` until picked in the boot menu.
+    pub role: Option<Role>,
+    /// What was on screen at the last power off, see [`crate::App::snapshot`].
+    pub snapshot: Option<Snapshot>,
+}
+
+impl Settings {
+    pub fn to_record(&self) -> Vec<u8> {
+        to_record(MAGIC, self)
+    }
+
+    pub fn from_record(record: &[u8]) -> Option<Self> {
+        from_record(MAGIC, record)
+    }
+}
+
+/// The record layout above, other records tell themselves apart by `magic`.
+pub(crate) fn to_record(magic: [u8; 4], value: &impl Serialize) -> Vec<u8> {
+    let payload = postcard::to_allocvec(value).unwrap();
+    let mut record = Vec::with_capacity(HEADER_SIZE + payload.len());
+    record.extend_from_slice(&magic);
+    record.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    record.extend_from_slice(&crc32(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    record
+}
+
+pub(crate) fn from_record<T: DeserializeOwned>(magic: [u8; 4], record: &[u8]) -> Option<T> {
+    let (header, rest) = record.split_at_checked(HEADER_SIZE)?;
+    if header[..4] != magic {
+        return None;
+    }
+    let len = u16::from_le_bytes([header[4], header[5]]) as usize;
+    let crc = u32::from_le_bytes(header[6..10].try_into().ok()?);
+    let payload = rest.get(..len)?;
+    if crc32(payload) != crc {
+        return None;
+    }
+    postcard::from_bytes(payload).ok()
+}
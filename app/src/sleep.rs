@@ -0,0 +1,44 @@
+//! Deep sleep between uses, for the remote mostly.
+//!
+//! Once [`crate::idle`] turns the screen off, an app built with
+//! [`App::with_deep_sleep`] publishes [`Event::DeepSleep`] and returns from `run`. Its
+//! [`Snapshot`] goes somewhere that survives the sleep (RTC memory on the stick), the next
+//! app comes back where this one left off with [`App::with_snapshot`].
+//!
+//! [`App::with_deep_sleep`]: crate::App::with_deep_sleep
+//! [`App::with_snapshot`]: crate::App::with_snapshot
+//! [`Event::DeepSleep`]: crate::events::Event::DeepSleep
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{events::Remote, settings};
+
+/// Bump when [`Snapshot`] changes shape.
+const MAGIC: [u8; 4] = *b"SNP1";
+/// Upper bound of a record, platforms can reserve this much.
+pub const MAX_RECORD_SIZE: usize = 64;
+
+/// What was on screen when the stick went to sleep.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Title of the selected tab.
+    pub tab: String,
+    /// The button the TV remote was on.
+    pub remote: Option<Remote>,
+}
+
+impl Snapshot {
+    pub fn to_record(&self) -> Vec<u8> {
+        settings::to_record(MAGIC, self)
+    }
+
+    /// `None` for anything that isn't a snapshot, say RTC memory after a cold boot.
+    pub fn from_record(record: &[u8]) -> Option<Self> {
+        settings::from_record(MAGIC, record)
+    }
+}
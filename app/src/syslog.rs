@@ -0,0 +1,142 @@
+//! RFC 5424 syslog formatting for the remote log sink.
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::string::String;
+
+use core::fmt::Write;
+
+use embassy_time::Instant;
+use log::Level;
+
+use crate::{
+    clock::{self, DateTime},
+    logger::RemoteRecord,
+};
+
+pub const SYSLOG_PORT: u16 = 514;
+
+/// local0
+const FACILITY: u8 = 16;
+/// RFC 5424 caps MSGID at 32 characters.
+const MSGID_MAX: usize = 32;
+
+/// At most `max` bytes of `s`, cut where a char starts.
+fn truncate(s: &str, max: usize) -> &str {
+    let end = (0..=max.min(s.len()))
+        .rev()
+        .find(|i| s.is_char_boundary(*i))
+        .unwrap_or(0);
+    &s[..end]
+}
+
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [SD] MSG`
+///
+/// The timestamp is nil until the [`clock`] is synced, the uptime always goes to structured data
+/// as `sysUpTime` of the `meta` SD-ID, in hundredths of a second (RFC 5424 7.3.2).
+/// The log target is used as MSGID.
+pub fn format(record: &RemoteRecord, hostname: &str, app_name: &str) -> String {
+    let unix_ms = clock::unix_ms_at(Instant::from_millis(record.uptime_ms));
+    format_at(record, unix_ms, hostname, app_name)
+}
+
+fn format_at(
+    record: &RemoteRecord,
+    unix_ms: Option<u64>,
+    hostname: &str,
+    app_name: &str,
+) -> String {
+    let mut line = String::with_capacity(record.msg.len() + 96);
+    _ = write!(line, "<{}>1 ", FACILITY * 8 + severity(record.level));
+    match unix_ms {
+        Some(ms) => _ = write!(line, "{} ", DateTime::from_unix((ms / 1000) as i64, 0)),
+        None => line.push_str("- "),
+    }
+    _ = write!(
+        line,
+        "{} {} - {} [meta sysUpTime=\"{}\"] {}",
+        hostname,
+        app_name,
+        truncate(&record.target, MSGID_MAX),
+        record.uptime_ms / 10,
+        record.msg
+    );
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(level: Level, target: &str) -> RemoteRecord {
+        RemoteRecord {
+            level,
+            target: target.into(),
+            msg: "hello".into(),
+            uptime_ms: 12_345,
+        }
+    }
+
+    #[test]
+    fn pri_of_each_level() {
+        for (level, pri) in [
+            (Level::Error, "<131>"),
+            (Level::Warn, "<132>"),
+            (Level::Info, "<134>"),
+            (Level::Debug, "<135>"),
+            (Level::Trace, "<135>"),
+        ] {
+            let line = format_at(&record(level, "app"), None, "stick", "fw");
+            assert!(line.starts_with(pri), "{:?}: {}", level, line);
+        }
+    }
+
+    #[test]
+    fn nil_timestamp_until_synced() {
+        assert_eq!(
+            format_at(&record(Level::Info, "app::rpc"), None, "stick", "fw"),
+            "<134>1 - stick fw - app::rpc [meta sysUpTime=\"1234\"] hello"
+        );
+    }
+
+    #[test]
+    fn timestamp_once_synced() {
+        assert_eq!(
+            format_at(
+                &record(Level::Warn, "app"),
+                Some(1_760_796_187_500),
+                "stick",
+                "fw"
+            ),
+            "<132>1 2025-10-18T14:03:07Z stick fw - app [meta sysUpTime=\"1234\"] hello"
+        );
+    }
+
+    #[test]
+    fn msgid_cut_to_32_bytes() {
+        let target = "a".repeat(40);
+        let line = format_at(&record(Level::Info, &target), None, "stick", "fw");
+        assert!(line.contains(&format!(" {} [meta", "a".repeat(MSGID_MAX))));
+    }
+
+    #[test]
+    fn truncated_on_a_char_boundary() {
+        assert_eq!(truncate("abc", 5), "abc");
+        assert_eq!(truncate("abc", 2), "ab");
+        // `é` is two bytes, half of it isn't kept
+        assert_eq!(truncate("aé", 2), "a");
+        assert_eq!(truncate("aé", 3), "aé");
+        assert_eq!(truncate("日本", 4), "日");
+        assert_eq!(truncate("日本", 2), "");
+    }
+}
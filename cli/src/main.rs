@@ -0,0 +1,526 @@
+use std::{
+    cell::Cell,
+    fs, io,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use app::{
+    Remote, Vehicle,
+    auth::{self, AuthError, Key, Token},
+    ota::{self, OtaError},
+    pager::Page,
+    rpc::{self, Request, Response},
+};
+use clap::{Parser, Subcommand};
+
+/// Talk to sticks (or the simulator) over the UDP RPC protocol.
+#[derive(Parser)]
+struct Cli {
+    /// Stick address, the AP gateway by default.
+    #[arg(long, default_value = "192.168.2.1")]
+    host: Ipv4Addr,
+    #[arg(long, default_value_t = rpc::RPC_PORT)]
+    port: u16,
+    /// Response timeout in milliseconds.
+    #[arg(long, default_value_t = 2000)]
+    timeout: u64,
+    /// Session from `pair`, its token and key in hex.
+    #[arg(long, env = "STICK_SESSION", value_parser = parse_session)]
+    session: Option<Session>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Pair with the stick using the PIN it shows, prints the session.
+    Pair {
+        #[arg(long, default_value = "cli")]
+        name: String,
+    },
+    /// Broadcast a ping and list every stick that answers.
+    Discover {
+        #[arg(long, default_value = "255.255.255.255")]
+        broadcast: Ipv4Addr,
+    },
+    /// Send a TV remote key, e.g. `on-off`, `volume-up`.
+    Remote {
+        #[arg(value_parser = parse_remote)]
+        key: Remote,
+    },
+    /// Drive a vehicle, speeds are -100..100.
+    Vehicle {
+        #[arg(allow_hyphen_values = true)]
+        left: i8,
+        #[arg(allow_hyphen_values = true)]
+        right: i8,
+    },
+    /// Print the log ring.
+    Logs {
+        /// Keep polling for new lines.
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Print battery and heap stats.
+    Stats,
+    /// Flash a firmware image, e.g. from `espflash save-image`, and reboot into it.
+    Ota { image: PathBuf },
+    /// Show a message in the stick's inbox.
+    Page {
+        text: String,
+        #[arg(long, default_value = "cli")]
+        from: String,
+        /// Have the stick broadcast it to the sticks around it over ESP-NOW instead.
+        #[arg(long)]
+        relay: bool,
+    },
+}
+
+fn parse_remote(key: &str) -> Result<Remote, String> {
+    key.parse()
+        .map_err(|_| format!("unknown remote key `{key}`"))
+}
+
+#[derive(Clone, Copy)]
+struct Session {
+    token: Token,
+    key: Key,
+}
+
+fn parse_session(session: &str) -> Result<Session, String> {
+    let bytes = (0..session.len())
+        .step_by(2)
+        .map(|i| {
+            session
+                .get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|bytes| bytes.len() == size_of::<Token>() + size_of::<Key>())
+        .ok_or_else(|| "expected 96 hex digits".to_string())?;
+    let (token, key) = bytes.split_at(size_of::<Token>());
+    Ok(Session {
+        token: token.try_into().unwrap(),
+        key: key.try_into().unwrap(),
+    })
+}
+
+struct Client {
+    socket: UdpSocket,
+    target: SocketAddr,
+    session: Option<Session>,
+    /// Of the last authenticated request.
+    counter: Cell<u64>,
+}
+
+impl Client {
+    fn new(target: SocketAddr, timeout: Duration, session: Option<Session>) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_read_timeout(Some(timeout))?;
+        Ok(Self {
+            socket,
+            target,
+            session,
+            counter: Cell::new(0),
+        })
+    }
+
+    /// The stick wants a higher counter with every request, the clock keeps it going up
+    /// from one run to the next.
+    fn next_counter(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_micros() as u64);
+        let counter = now.max(self.counter.get() + 1);
+        self.counter.set(counter);
+        counter
+    }
+
+    /// Wrapped in the session, if there is one.
+    fn send(&self, request: &Request) -> io::Result<()> {
+        let authenticated;
+        let request = match (self.session, request) {
+            (None, _)
+            | (
+                _,
+                Request::Ping
+                | Request::PairBegin { .. }
+                | Request::Pair { .. }
+                | Request::Authenticated { .. },
+            ) => request,
+            (Some(session), _) => {
+                authenticated = request
+                    .authenticated(session.token, &session.key, self.next_counter())
+                    .map_err(io::Error::other)?;
+                &authenticated
+            }
+        };
+        let mut buf = [0u8; rpc::MAX_PACKET_SIZE];
+        let msg = rpc::encode(request, &mut buf).map_err(io::Error::other)?;
+        self.socket.send_to(msg, self.target)?;
+        Ok(())
+    }
+
+    fn recv(&self) -> io::Result<(Response, SocketAddr)> {
+        let mut buf = [0u8; rpc::MAX_PACKET_SIZE];
+        let (len, from) = self.socket.recv_from(&mut buf)?;
+        let response = rpc::decode(&buf[..len]).map_err(io::Error::other)?;
+        Ok((response, from))
+    }
+
+    fn request(&self, request: &Request) -> io::Result<Response> {
+        self.send(request)?;
+        self.recv().map(|(response, _)| response)
+    }
+
+    /// Like [`Client::request`], but resends on timeout. Only for idempotent requests.
+    fn request_with_retries(&self, request: &Request, retries: usize) -> io::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            match self.request(request) {
+                Err(e) if is_timeout(&e) && attempt < retries => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+fn discover(client: &Client, timeout: Duration) -> io::Result<()> {
+    client.socket.set_broadcast(true)?;
+    client.send(&Request::Ping)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut found = 0;
+    while Instant::now() < deadline {
+        match client.recv() {
+            Ok((Response::Pong { name, version }, from)) => {
+                println!("{from}\t{name}\tv{version}");
+                found += 1;
+            }
+            Ok(_) => {}
+            Err(e) if is_timeout(&e) => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    if found == 0 {
+        eprintln!("no sticks found");
+    }
+    Ok(())
+}
+
+fn pair(client: &Client, name: &str) -> io::Result<()> {
+    let nonce = match client.request(&Request::PairBegin {
+        client: name.into(),
+    })? {
+        Response::PairChallenge { nonce } => nonce,
+        response => return unexpected(response),
+    };
+
+    eprint!("PIN on the stick's screen: ");
+    let mut pin = String::new();
+    io::stdin().read_line(&mut pin)?;
+    let pin = pin
+        .replace(char::is_whitespace, "")
+        .parse()
+        .map_err(|_| io::Error::other("the PIN is six digits"))?;
+
+    match client.request(&Request::Pair {
+        proof: auth::proof(&nonce, pin),
+    })? {
+        Response::Paired { token } => {
+            let key = auth::session_key(&nonce, pin);
+            println!("export STICK_SESSION={}{}", hex(&token), hex(&key));
+            Ok(())
+        }
+        response => unexpected(response),
+    }
+}
+
+fn logs(client: &Client, follow: bool) -> io::Result<()> {
+    let mut after = 0;
+    loop {
+        match client.request(&Request::Logs { after })? {
+            Response::Logs(lines) => {
+                for line in &lines {
+                    println!("[{}] {}", line.level, line.msg);
+                }
+                if let Some(last) = lines.last() {
+                    after = last.seq;
+                    // the response was cut to fit a packet, fetch the rest right away
+                    continue;
+                }
+            }
+            response => return unexpected(response),
+        }
+
+        if !follow {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+fn ota(client: &Client, image: &Path) -> io::Result<()> {
+    const RETRIES: usize = 5;
+
+    let image = fs::read(image)?;
+    let size = u32::try_from(image.len()).map_err(io::Error::other)?;
+    println!("{} bytes, sha256 {}", size, hex(&ota::sha256(&image)));
+
+    expect_ok(client.request(&Request::OtaBegin {
+        size,
+        sha256: ota::sha256(&image),
+    })?)?;
+
+    let mut offset = 0;
+    while offset < size {
+        let start = offset as usize;
+        let chunk = &image[start..(start + ota::CHUNK_SIZE).min(image.len())];
+        let request = Request::OtaChunk {
+            offset,
+            crc32: ota::crc32(chunk),
+            data: chunk.to_vec(),
+        };
+        match client.request_with_retries(&request, RETRIES)? {
+            Response::OtaAck { next } => offset = next,
+            // a late ack got us out of step
+            Response::OtaError(OtaError::UnexpectedOffset { expected }) => offset = expected,
+            // corrupted on the way, send it again
+            Response::OtaError(OtaError::ChunkCrc) => {}
+            response => return unexpected(response),
+        }
+        eprint!("\r{:3}%", offset as u64 * 100 / size as u64);
+    }
+    eprintln!();
+
+    expect_ok(client.request_with_retries(&Request::OtaFinish, RETRIES)?)?;
+    println!("image verified, rebooting");
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn expect_ok(response: Response) -> io::Result<()> {
+    match response {
+        Response::Ok => Ok(()),
+        response => unexpected(response),
+    }
+}
+
+fn unexpected(response: Response) -> io::Result<()> {
+    Err(io::Error::other(match response {
+        Response::AuthError(AuthError::Unauthorized) => {
+            "not paired, run `pair` and set STICK_SESSION".to_string()
+        }
+        Response::AuthError(e) => e.to_string(),
+        response => format!("unexpected response: {response:?}"),
+    }))
+}
+
+fn main() -> io::Result<()> {
+    let cli = Cli::parse();
+    let timeout = Duration::from_millis(cli.timeout);
+
+    let target = match cli.command {
+        Command::Discover { broadcast } => broadcast,
+        _ => cli.host,
+    };
+    let client = Client::new(SocketAddr::from((target, cli.port)), timeout, cli.session)?;
+
+    match cli.command {
+        Command::Pair { name } => pair(&client, &name),
+        Command::Discover { .. } => discover(&client, timeout),
+        Command::Remote { key } => expect_ok(client.request(&Request::Remote(key))?),
+        Command::Vehicle { left, right } => expect_ok(client.request(&Request::Vehicle(
+            Vehicle::Move(left.clamp(-100, 100), right.clamp(-100, 100)),
+        ))?),
+        Command::Logs { follow } => logs(&client, follow),
+        Command::Stats => match client.request(&Request::Stats)? {
+            Response::Stats(stats) => {
+                println!(
+                    "battery\t{}% {} mV, {}",
+                    stats.battery_level,
+                    stats.battery_mv,
+                    <&str>::from(stats.power)
+                );
+                if let Some(minutes) = stats.minutes_left {
+                    println!("left\t{}h {}m", minutes / 60, minutes % 60);
+                }
+                println!(
+                    "heap\t{}/{} KiB",
+                    stats.heap_used / 1024,
+                    (stats.heap_used + stats.heap_free) / 1024
+                );
+                Ok(())
+            }
+            response => unexpected(response),
+        },
+        Command::Ota { image } => ota(&client, &image),
+        Command::Page { text, from, relay } => expect_ok(client.request(&Request::Page {
+            page: Page::new(&from, &text),
+            relay,
+        })?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use app::{Event, Stats, logger, rpc::RpcHandler};
+
+    use super::*;
+
+    fn session_hex() -> String {
+        format!("{}{}", hex(&[0x01; 16]), hex(&[0xab; 32]))
+    }
+
+    #[test]
+    fn session_round_trip() {
+        let session = parse_session(&session_hex()).unwrap();
+        assert_eq!(session.token, [0x01; 16]);
+        assert_eq!(session.key, [0xab; 32]);
+        assert_eq!(
+            format!("{}{}", hex(&session.token), hex(&session.key)),
+            session_hex()
+        );
+        assert!(parse_session(&session_hex().to_uppercase()).is_ok());
+    }
+
+    #[test]
+    fn session_of_the_wrong_length() {
+        let session = session_hex();
+        for wrong in ["", &session[..94], &format!("{session}00")] {
+            assert!(parse_session(wrong).is_err(), "{wrong}");
+        }
+    }
+
+    #[test]
+    fn session_not_in_hex() {
+        let session = session_hex();
+        let odd = &session[..95];
+        let not_hex = format!("zz{}", &session[2..]);
+        let not_ascii = format!("é{}", &session[2..]);
+        for wrong in [odd, &not_hex, &not_ascii] {
+            assert!(parse_session(wrong).is_err(), "{wrong}");
+        }
+    }
+
+    /// The simulator's RPC server: an [`RpcHandler`] behind a UDP socket. The PINs it would
+    /// show go to `pins`, the events it would publish to `events`.
+    fn serve(socket: UdpSocket, pins: mpsc::Sender<u32>, events: mpsc::Sender<Event>) {
+        let mut handler = RpcHandler::new("sim");
+        handler.update(&Event::StatsUpdated(Stats {
+            battery_level: 80,
+            battery_mv: 3900,
+            ..Default::default()
+        }));
+        let mut buf = [0u8; rpc::MAX_PACKET_SIZE];
+        let mut out = [0u8; rpc::MAX_PACKET_SIZE];
+        loop {
+            let (len, remote) = socket.recv_from(&mut buf).unwrap();
+            let response = match rpc::decode::<Request>(&buf[..len]) {
+                Ok(request) => {
+                    let (response, event) = handler.handle(request);
+                    match event {
+                        Some(Event::PairingPin(Some(pin))) => pins.send(pin.pin).unwrap(),
+                        // pairing is over
+                        Some(Event::PairingPin(None)) | None => {}
+                        Some(event) => events.send(event).unwrap(),
+                    }
+                    response
+                }
+                Err(_) => Response::Error("bad request".into()),
+            };
+            let msg = rpc::encode(&response, &mut out).unwrap();
+            socket.send_to(msg, remote).unwrap();
+        }
+    }
+
+    #[test]
+    fn against_the_simulator() {
+        logger::init();
+        auth::init(|buf| {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte = i as u8 * 7;
+            }
+        });
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let target = socket.local_addr().unwrap();
+        let (pins, pin) = mpsc::channel();
+        let (events, event) = mpsc::channel();
+        thread::spawn(move || serve(socket, pins, events));
+        let timeout = Duration::from_secs(2);
+
+        // nothing but a ping without a session
+        let client = Client::new(target, timeout, None).unwrap();
+        assert!(matches!(
+            client.request(&Request::Ping).unwrap(),
+            Response::Pong { name, .. } if name == "sim"
+        ));
+        let refused = unexpected(client.request(&Request::Stats).unwrap()).unwrap_err();
+        assert!(refused.to_string().contains("run `pair`"));
+
+        // what `pair` does, with the PIN off the screen
+        let Response::PairChallenge { nonce } = client
+            .request(&Request::PairBegin {
+                client: "test".into(),
+            })
+            .unwrap()
+        else {
+            panic!("no challenge");
+        };
+        let pin = pin.recv_timeout(timeout).unwrap();
+        let Response::Paired { token } = client
+            .request(&Request::Pair {
+                proof: auth::proof(&nonce, pin),
+            })
+            .unwrap()
+        else {
+            panic!("not paired");
+        };
+        let session = Session {
+            token,
+            key: auth::session_key(&nonce, pin),
+        };
+        let client = Client::new(target, timeout, Some(session)).unwrap();
+
+        match client.request(&Request::Stats).unwrap() {
+            Response::Stats(stats) => {
+                assert_eq!(stats.battery_level, 80);
+                assert_eq!(stats.battery_mv, 3900);
+            }
+            response => panic!("{response:?}"),
+        }
+
+        let key = parse_remote("volume-up").unwrap();
+        expect_ok(client.request(&Request::Remote(key)).unwrap()).unwrap();
+        assert!(matches!(
+            event.recv_timeout(timeout).unwrap(),
+            Event::Remote(remote) if remote == key
+        ));
+
+        log::info!("hello from the test");
+        match client.request(&Request::Logs { after: 0 }).unwrap() {
+            Response::Logs(lines) => {
+                assert!(lines.iter().any(|line| line.msg == "hello from the test"));
+                assert!(lines.iter().any(|line| line.msg == "paired test"));
+            }
+            response => panic!("{response:?}"),
+        }
+        logs(&client, false).unwrap();
+    }
+}
@@ -1,7 +1,21 @@
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, TcpStream, UdpSocket},
+    sync::atomic::{AtomicU8, Ordering},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use app::{
-    App, Sender,
+    App, Sender, auth, backlight, clock,
+    dashboard::{self, Field, Url},
+    discovery,
     events::{self, EVENTS, Receiver},
+    idle::IdleState,
     logger,
+    ota::MemFlash,
+    role,
+    rpc::{self, Request, Response, RpcHandler},
 };
 use embassy_executor::Spawner;
 use embassy_time::{Duration, Timer};
@@ -9,7 +23,7 @@ use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
 use embedded_graphics_simulator::{
     OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window, sdl2,
 };
-use log::{debug, info};
+use log::{debug, info, warn};
 use mousefood::prelude::*;
 use ratatui::Terminal;
 
@@ -21,6 +35,40 @@ async fn draw_task(sender: Sender) {
     }
 }
 
+/// In percent, the window dims the framebuffer by it like the backlight would.
+static BRIGHTNESS: AtomicU8 = AtomicU8::new(backlight::DEFAULT);
+
+/// Follows the picked brightness and the idle state, dark while the screen is off.
+#[embassy_executor::task]
+async fn backlight_task(mut receiver: Receiver) {
+    let mut brightness = backlight::DEFAULT;
+    let mut idle = IdleState::Active;
+    loop {
+        match receiver.next_message_pure().await {
+            app::Event::SetBrightness(percent) => brightness = percent,
+            app::Event::Idle(state) => idle = state,
+            _ => continue,
+        }
+        BRIGHTNESS.store(idle.backlight(brightness).min(100), Ordering::Relaxed);
+    }
+}
+
+/// A copy of `display` with every pixel scaled down to `percent`.
+fn dimmed(display: &SimulatorDisplay<Rgb565>, percent: u8) -> SimulatorDisplay<Rgb565> {
+    let scale = |c: u8| (c as u16 * percent as u16 / 100) as u8;
+    let mut dimmed = SimulatorDisplay::new(display.size());
+    dimmed
+        .draw_iter(display.bounding_box().points().map(|point| {
+            let color = display.get_pixel(point);
+            Pixel(
+                point,
+                Rgb565::new(scale(color.r()), scale(color.g()), scale(color.b())),
+            )
+        }))
+        .unwrap();
+    dimmed
+}
+
 #[embassy_executor::task]
 async fn event_handler(mut receiver: Receiver) {
     loop {
@@ -31,6 +79,270 @@ async fn event_handler(mut receiver: Receiver) {
     }
 }
 
+/// Answers `Event::Scan` with a made-up survey, so the scan tab can be worked on without hardware.
+#[embassy_executor::task]
+async fn scan_task(sender: Sender, mut receiver: Receiver) {
+    use events::{AccessPoint, AuthMode};
+
+    const NETWORKS: [(&str, u8, i8, AuthMode); 10] = [
+        ("lab", 6, -42, AuthMode::Wpa2),
+        ("lab-guest", 6, -47, AuthMode::Open),
+        ("FRITZ!Box 7590 XY", 1, -63, AuthMode::Wpa2Wpa3),
+        ("", 1, -70, AuthMode::Wpa2),
+        ("DIRECT-5C-HP LaserJet", 6, -71, AuthMode::Wpa2),
+        ("eduroam", 11, -58, AuthMode::Enterprise),
+        ("stick", 11, -35, AuthMode::Open),
+        ("printer-setup", 3, -80, AuthMode::Wep),
+        ("neighbour", 9, -86, AuthMode::WpaWpa2),
+        ("iot", 13, -77, AuthMode::Wpa3),
+    ];
+
+    loop {
+        if !matches!(receiver.next_message_pure().await, app::Event::Scan) {
+            continue;
+        }
+        // a real scan hops through all channels
+        Timer::after(Duration::from_millis(1500)).await;
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        let access_points = NETWORKS
+            .iter()
+            .enumerate()
+            .map(|(i, (ssid, channel, rssi, auth))| AccessPoint {
+                ssid: ssid.to_string(),
+                bssid: [0x02, 0x00, 0x5e, 0x10, *channel, i as u8],
+                channel: *channel,
+                // signals wobble between scans
+                rssi: rssi.saturating_add((seed >> (i * 3)) as i8 % 5),
+                auth: *auth,
+            })
+            .collect();
+        sender.publish(app::Event::ScanResults(access_points)).await;
+    }
+}
+
+/// Feeds `app::monitor` made-up frames: two ESP-NOW controller/vehicle pairs fighting over
+/// channel 11 next to some ordinary Wi-Fi traffic.
+#[embassy_executor::task]
+async fn monitor_task(sender: Sender) {
+    use app::monitor::Monitor;
+
+    // (source, channel, rssi, frames per second, ESP-NOW)
+    const SOURCES: [([u8; 6], u8, i8, u32, bool); 6] = [
+        ([0x24, 0x0a, 0xc4, 0x10, 0x00, 0x01], 11, -38, 40, true),
+        ([0x24, 0x0a, 0xc4, 0x10, 0x00, 0x02], 11, -45, 12, true),
+        ([0x24, 0x0a, 0xc4, 0x20, 0x00, 0x01], 11, -61, 40, true),
+        ([0x24, 0x0a, 0xc4, 0x20, 0x00, 0x02], 11, -66, 12, true),
+        ([0x02, 0x00, 0x5e, 0x10, 0x0b, 0x06], 11, -35, 10, false),
+        ([0x02, 0x00, 0x5e, 0x10, 0x06, 0x00], 6, -42, 25, false),
+    ];
+
+    let mut monitor = Monitor::new();
+    loop {
+        Timer::after(Duration::from_secs(1)).await;
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        for (i, (mac, channel, rssi, rate, esp_now)) in SOURCES.iter().enumerate() {
+            let mut frame = [0u8; 32];
+            if *esp_now {
+                // action frame, vendor specific, Espressif
+                frame[0] = 0xd0;
+                frame[24] = 127;
+                frame[25..28].copy_from_slice(&[0x18, 0xfe, 0x34]);
+            } else {
+                // data frame
+                frame[0] = 0x08;
+            }
+            frame[10..16].copy_from_slice(mac);
+            let jitter = (seed >> (i * 4)) % 11;
+            for _ in 0..rate + jitter {
+                monitor.record(*channel, *rssi, &frame);
+            }
+        }
+        sender
+            .publish(app::Event::PacketStats(monitor.take_stats()))
+            .await;
+    }
+}
+
+/// Polls `DASHBOARD_URL` like `stick::dashboard` does, on a thread so a slow server
+/// doesn't stall the UI.
+fn dashboard_thread(url: String, fields: String, token: Option<String>) {
+    let fields = dashboard::parse_fields(&fields);
+    let publisher = EVENTS.immediate_publisher();
+    loop {
+        let update = match poll_dashboard(&url, &fields, token.as_deref()) {
+            Ok(rows) => events::Dashboard { rows, error: None },
+            Err(e) => {
+                warn!("dashboard: {}", e);
+                events::Dashboard {
+                    rows: Vec::new(),
+                    error: Some(e),
+                }
+            }
+        };
+        publisher.publish_immediate(app::Event::Dashboard(update));
+        thread::sleep(std::time::Duration::from_secs(5));
+    }
+}
+
+fn poll_dashboard(
+    url: &str,
+    fields: &[Field],
+    token: Option<&str>,
+) -> Result<Vec<(String, String)>, String> {
+    let url = Url::parse(url).map_err(|e| e.to_string())?;
+    let mut stream = TcpStream::connect((url.host, url.port)).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .map_err(|e| e.to_string())?;
+    stream
+        .write_all(dashboard::request(&url, token).as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut response = Vec::new();
+    stream
+        .take(dashboard::MAX_RESPONSE_SIZE as u64)
+        .read_to_end(&mut response)
+        .map_err(|e| e.to_string())?;
+    let body = dashboard::parse_response(&response).map_err(|e| e.to_string())?;
+    dashboard::extract(body, fields).map_err(|e| e.to_string())
+}
+
+/// Stands in for `stick::discovery::discovery_task`, one simulator per host as the port
+/// isn't shared.
+#[embassy_executor::task]
+async fn discovery_task(sender: Sender, mut receiver: Receiver, role: app::Role) {
+    use discovery::{ANNOUNCE_INTERVAL, Announce, GROUP, MAX_ANNOUNCE_SIZE, PORT};
+
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("discovery: UDP bind failed: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = socket.join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED) {
+        warn!("discovery: can't join {}: {}", GROUP, e);
+        return;
+    }
+    socket.set_nonblocking(true).unwrap();
+
+    let mut me = Announce::new("sim", role);
+    let mut buf = [0u8; MAX_ANNOUNCE_SIZE];
+    let mut next_announce = embassy_time::Instant::now();
+
+    loop {
+        while let Some(event) = receiver.try_next_message_pure() {
+            me.update(&event);
+        }
+
+        if embassy_time::Instant::now() >= next_announce {
+            next_announce += ANNOUNCE_INTERVAL;
+            if let Some(len) = me.encode(&mut buf) {
+                _ = socket.send_to(&buf[..len], (GROUP, PORT));
+            }
+            if discovery::expire() {
+                peers_changed(&sender, &mut receiver, &mut me).await;
+            }
+        }
+
+        match socket.recv_from(&mut buf) {
+            Ok((len, std::net::SocketAddr::V4(remote))) => match Announce::decode(&buf[..len]) {
+                // multicast loops back to the sender
+                Some(announce) if announce.name != me.name => {
+                    if discovery::record(*remote.ip(), announce) {
+                        peers_changed(&sender, &mut receiver, &mut me).await;
+                    }
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                Timer::after(Duration::from_millis(100)).await;
+            }
+            Err(e) => warn!("discovery: recv error: {}", e),
+        }
+    }
+}
+
+/// Keeps reading the bus while it's full, we're one of the subscribers it waits on.
+async fn peers_changed(sender: &Sender, receiver: &mut Receiver, me: &mut discovery::Announce) {
+    while sender.try_publish(app::Event::PeersChanged).is_err() {
+        while let Some(event) = receiver.try_next_message_pure() {
+            me.update(&event);
+        }
+        Timer::after(Duration::from_millis(20)).await;
+    }
+}
+
+/// Stands in for `stick::server::rpc_server`, so the host `cli` can be tested against the simulator.
+#[embassy_executor::task]
+async fn rpc_task(sender: Sender, mut receiver: Receiver) {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, rpc::RPC_PORT)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("UDP bind failed: {}", e);
+            return;
+        }
+    };
+    socket.set_nonblocking(true).unwrap();
+    info!("UDP RPC server listening on port {}", rpc::RPC_PORT);
+
+    // images land in memory, enough to try `cli ota` end to end
+    let mut handler = RpcHandler::new("sim").with_ota(MemFlash::new(0x30_0000));
+    let mut buf = [0u8; rpc::MAX_PACKET_SIZE];
+    let mut out = [0u8; rpc::MAX_PACKET_SIZE];
+
+    loop {
+        while let Some(event) = receiver.try_next_message_pure() {
+            handler.update(&event);
+        }
+
+        let (len, remote) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                Timer::after(Duration::from_millis(20)).await;
+                continue;
+            }
+            Err(e) => {
+                warn!("recv error: {}", e);
+                continue;
+            }
+        };
+
+        let response = match rpc::decode::<Request>(&buf[..len]) {
+            Ok(request) => {
+                let (response, event) = handler.handle(request);
+                if let Some(mut event) = event {
+                    // keep reading while the bus is full, or we'd wait on ourselves
+                    while let Err(full) = sender.try_publish(event) {
+                        while let Some(seen) = receiver.try_next_message_pure() {
+                            handler.update(&seen);
+                        }
+                        Timer::after(Duration::from_millis(20)).await;
+                        event = full;
+                    }
+                }
+                response
+            }
+            Err(e) => {
+                warn!("bad request from {}: {:?}", remote, e);
+                Response::Error("bad request".into())
+            }
+        };
+
+        if let Ok(msg) = rpc::encode(&response, &mut out) {
+            _ = socket.send_to(msg, remote);
+        }
+    }
+}
+
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     // env_logger::builder()
@@ -38,6 +350,28 @@ async fn main(spawner: Spawner) {
     //     .format_timestamp_nanos()
     //     .init();
     logger::init();
+    // RandomState is seeded from the OS
+    auth::init(|buf| {
+        use std::hash::{BuildHasher, RandomState};
+        for chunk in buf.chunks_mut(8) {
+            let random = RandomState::new().hash_one(0u8).to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    });
+
+    // the host clock stands in for SNTP
+    clock::set_unix_time(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+    );
+    if let Some(offset) = std::env::var("UTC_OFFSET")
+        .ok()
+        .and_then(|offset| clock::parse_utc_offset(&offset))
+    {
+        clock::set_utc_offset(offset);
+    }
 
     let output_settings = OutputSettingsBuilder::new().scale(3).build();
     let mut window = Window::new(
@@ -47,11 +381,14 @@ async fn main(spawner: Spawner) {
 
     let mut display: SimulatorDisplay<Rgb565> = SimulatorDisplay::new(Size::new(240, 135));
 
-    let btn_sender = EVENTS.publisher().unwrap();
+    let btn_sender = events::sender();
 
     let backend_config = EmbeddedBackendConfig {
         flush_callback: Box::new(move |display| {
-            window.update(display);
+            match BRIGHTNESS.load(Ordering::Relaxed) {
+                100 => window.update(display),
+                percent => window.update(&dimmed(display, percent)),
+            }
 
             for event in window.events() {
                 match event {
@@ -109,21 +446,57 @@ async fn main(spawner: Spawner) {
 
     let mut terminal = Terminal::new(backend).unwrap();
 
-    let mut app = App::new();
+    spawner.spawn(event_handler(events::receiver())).unwrap();
+
+    spawner.spawn(draw_task(events::sender())).unwrap();
+
+    spawner.spawn(backlight_task(events::receiver())).unwrap();
 
     spawner
-        .spawn(event_handler(EVENTS.subscriber().unwrap()))
+        .spawn(scan_task(events::sender(), events::receiver()))
         .unwrap();
 
+    spawner.spawn(monitor_task(events::sender())).unwrap();
+
     spawner
-        .spawn(draw_task(EVENTS.publisher().unwrap()))
+        .spawn(rpc_task(events::sender(), events::receiver()))
         .unwrap();
 
-    EVENTS
-        .publisher()
-        .unwrap()
+    // e.g. `python3 -m http.server` next to a `status.json` as a stand-in
+    if let Ok(url) = std::env::var("DASHBOARD_URL") {
+        let fields = std::env::var("DASHBOARD_FIELDS").unwrap_or("status=/status".into());
+        let token = std::env::var("DASHBOARD_TOKEN").ok();
+        thread::spawn(move || dashboard_thread(url, fields, token));
+    }
+
+    // `ROLE=vehicle` skips the boot menu, there's no storage to remember it
+    let role = match std::env::var("ROLE")
+        .ok()
+        .and_then(|role| role.parse().ok())
+    {
+        Some(role) => role,
+        None => {
+            let mut receiver = events::receiver();
+            role::select_role(&mut terminal, &mut receiver, None)
+                .await
+                .unwrap()
+        }
+    };
+
+    spawner
+        .spawn(discovery_task(events::sender(), events::receiver(), role))
+        .unwrap();
+
+    let mut app = App::new(role);
+
+    events::sender()
         .publish(app::Event::InitHat(app::StickHat::MiniJoyC))
         .await;
 
     app.run(&mut terminal).await.unwrap();
+
+    // a stick would cut its power here, let the goodbye screen show
+    Timer::after(Duration::from_secs(1)).await;
+    info!("powered off");
+    std::process::exit(0);
 }
@@ -0,0 +1,77 @@
+//! ST7789 backlight on GPIO27, dimmed with LEDC PWM.
+//!
+//! Follows the brightness picked in the app, and [`app::idle`] while nobody is around.
+
+use app::{
+    backlight,
+    events::{Event, Receiver},
+    idle::IdleState,
+};
+use esp_hal::{
+    gpio::{DriveMode, interconnect::PeripheralOutput},
+    ledc::{
+        LSGlobalClkSource, Ledc, LowSpeed,
+        channel::{self, Channel, ChannelIFace},
+        timer::{self, Timer, TimerIFace},
+    },
+    peripherals::LEDC,
+    time::Rate,
+};
+use static_cell::StaticCell;
+
+static LEDC: StaticCell<Ledc<'static>> = StaticCell::new();
+static TIMER: StaticCell<Timer<'static, LowSpeed>> = StaticCell::new();
+
+pub struct Backlight {
+    channel: Channel<'static, LowSpeed>,
+}
+
+impl Backlight {
+    /// Comes up at [`backlight::DEFAULT`].
+    pub fn new(ledc: LEDC<'static>, pin: impl PeripheralOutput<'static>) -> Self {
+        let ledc = LEDC.init(Ledc::new(ledc));
+        ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+        // well above what the eye or the camera notices
+        let timer = TIMER.init(ledc.timer::<LowSpeed>(timer::Number::Timer0));
+        timer
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty8Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: Rate::from_khz(20),
+            })
+            .unwrap();
+
+        let mut channel = ledc.channel(channel::Number::Channel0, pin);
+        channel
+            .configure(channel::config::Config {
+                timer: &*timer,
+                duty_pct: backlight::DEFAULT,
+                drive_mode: DriveMode::PushPull,
+            })
+            .unwrap();
+
+        Self { channel }
+    }
+
+    pub fn set(&mut self, percent: u8) {
+        if let Err(e) = self.channel.set_duty(percent.min(100)) {
+            log::warn!("backlight: can't set {}%: {:?}", percent, e);
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn backlight_task(mut receiver: Receiver, mut backlight: Backlight) {
+    let mut brightness = backlight::DEFAULT;
+    let mut idle = IdleState::Active;
+    loop {
+        match receiver.next_message_pure().await {
+            Event::SetBrightness(percent) => brightness = percent,
+            Event::Idle(state) => idle = state,
+            _ => continue,
+        }
+        log::debug!("backlight {}%, {:?}", brightness, idle);
+        backlight.set(idle.backlight(brightness));
+    }
+}
@@ -1,16 +1,43 @@
-// This is Claude slop, I have no idea what's happening
-// https://github.com/m5stack/M5Unified/blob/master/src/utility/Power_Class.cpp#L1266
+//! Battery voltage on GPIO38, through a divider by two. [`app::battery::Gauge`] makes a
+//! level out of it.
+//!
+//! The ADC correction is the one M5Unified uses for the StickC Plus2:
+//! https://github.com/m5stack/M5Unified/blob/master/src/utility/Power_Class.cpp#L1266
 
+use app::battery::Limits;
 use esp_hal::{
     Blocking,
     analog::adc::{Adc, AdcPin},
     peripherals::{ADC1, GPIO38},
 };
 
-pub fn get_battery_level(
+/// Warn at this level, in percent, override with `BATTERY_WARN=15` at build time.
+pub const WARN_LEVEL: Option<&str> = option_env!("BATTERY_WARN");
+/// Power off at this voltage, override with `BATTERY_CRITICAL_MV=3450` at build time.
+pub const CRITICAL_MV: Option<&str> = option_env!("BATTERY_CRITICAL_MV");
+
+/// [`Limits`] with the overrides above, the defaults for the ones that don't parse.
+pub fn limits() -> Limits {
+    let mut limits = Limits::default();
+    if let Some(level) = WARN_LEVEL {
+        match level.parse() {
+            Ok(level) => limits.warn_level = level,
+            Err(_) => log::warn!("Invalid BATTERY_WARN '{}'", level),
+        }
+    }
+    if let Some(mv) = CRITICAL_MV {
+        match mv.parse() {
+            Ok(mv) => limits.critical_mv = mv,
+            Err(_) => log::warn!("Invalid BATTERY_CRITICAL_MV '{}'", mv),
+        }
+    }
+    limits
+}
+
+pub fn read_battery_mv(
     adc: &mut Adc<'static, ADC1<'static>, Blocking>,
     pin: &mut AdcPin<GPIO38<'static>, ADC1<'static>>,
-) -> u8 {
+) -> u16 {
     let adc_value: u16 = nb::block!(adc.read_oneshot(pin)).unwrap();
 
     let adc_mv = {
@@ -19,15 +46,13 @@ pub fn get_battery_level(
         ((COEFF_A * adc_value as u32 + 32768) / 65536 + COEFF_B) as u16
     };
     let battery_mv = adc_mv * 2;
-    let level = ((battery_mv as i32 - 3300) * 100 / (4150 - 3350)).clamp(0, 100) as u8;
 
-    log::info!(
-        "Battery: raw={}, adc_mv={}, battery_mv={}, level={}%",
+    log::trace!(
+        "Battery: raw={}, adc_mv={}, battery_mv={}",
         adc_value,
         adc_mv,
-        battery_mv,
-        level
+        battery_mv
     );
 
-    level
+    battery_mv
 }
@@ -8,9 +8,10 @@
 #![deny(clippy::large_stack_frames)]
 
 use alloc::boxed::Box;
-use app::{App, EVENTS, Event, Sender, Stats, logger};
+use app::battery::Gauge;
+use app::{App, Event, Exit, Role, Sender, Stats, StickHat, events, logger, role};
 use embassy_executor::Spawner;
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
 use embedded_hal_bus::spi::ExclusiveDevice;
 use esp_alloc::HEAP;
@@ -20,6 +21,7 @@ use esp_hal::analog::adc::{Adc, AdcConfig, AdcPin, Attenuation};
 use esp_hal::gpio::{Input, InputConfig, Level, Output, OutputConfig, Pull};
 use esp_hal::i2c::master::I2c;
 use esp_hal::peripherals::{ADC1, GPIO38};
+use esp_hal::rtc_cntl::Rtc;
 use esp_hal::spi::master::{Config, Spi};
 use esp_hal::time::Rate;
 use esp_hal::timer::timg::TimerGroup;
@@ -30,8 +32,9 @@ use mipidsi::options::{ColorInversion, Orientation, Rotation};
 use mousefood::EmbeddedBackend;
 use mousefood::EmbeddedBackendConfig;
 use ratatui::Terminal;
-use stick::battery::get_battery_level;
+use stick::battery::read_battery_mv;
 use stick::button::Buttons;
+use stick::sleep::Wake;
 
 use stick::minijoyc::MiniJoyC;
 
@@ -49,10 +52,46 @@ macro_rules! mk_static {
 
 esp_bootloader_esp_idf::esp_app_desc!();
 
+/// Event publishers taken below with every task of the build running: the buttons, the stats,
+/// the app, the MiniJoyC and IR of the remote, and one per network task.
+const PUBLISHERS: usize = 5
+    + cfg!(feature = "now") as usize
+    + cfg!(feature = "scan") as usize
+    + cfg!(feature = "monitor") as usize
+    + 2 * cfg!(feature = "server") as usize
+    + (cfg!(feature = "server") || cfg!(feature = "client")) as usize
+    + cfg!(feature = "client") as usize
+    + cfg!(feature = "mqtt") as usize
+    + cfg!(feature = "dashboard") as usize
+    + cfg!(feature = "discovery") as usize;
+/// Event subscribers taken below: the backlight, the app, the IR or vehicle task, and the
+/// network tasks that take commands.
+const SUBSCRIBERS: usize = 3
+    + cfg!(feature = "now") as usize
+    + cfg!(feature = "scan") as usize
+    + 2 * cfg!(feature = "server") as usize
+    + cfg!(feature = "mqtt") as usize
+    + cfg!(feature = "discovery") as usize;
+const _: () = assert!(
+    PUBLISHERS <= events::MAX_PUBLISHERS,
+    "raise app::events::MAX_PUBLISHERS"
+);
+const _: () = assert!(
+    SUBSCRIBERS <= events::MAX_SUBSCRIBERS,
+    "raise app::events::MAX_SUBSCRIBERS"
+);
+
 #[embassy_executor::task]
-async fn buttons_task(mut button: Buttons) {
+async fn buttons_task(mut button: Buttons, mut rtc: Rtc<'static>) {
+    // the app gets a moment to see the press that woke us before the next sleep
+    let mut awake_until = Instant::now();
     loop {
         button.update().await;
+        if stick::idle::LIGHT_SLEEP && stick::idle::panel_asleep() && Instant::now() >= awake_until
+        {
+            button.sleep_until_pressed(&mut rtc);
+            awake_until = Instant::now() + Duration::from_secs(1);
+        }
         Timer::after(Duration::from_millis(50)).await;
     }
 }
@@ -63,17 +102,32 @@ async fn stats_task(
     mut pin: AdcPin<GPIO38<'static>, ADC1<'static>>,
     sender: Sender,
 ) {
+    // the gauge wants a few readings to average, the app only every so often
+    const READ_EVERY: Duration = Duration::from_secs(5);
+    const PUBLISH_EVERY: u32 = 6;
+
+    let mut gauge = Gauge::new();
+    let mut reads = 0;
     loop {
-        let level = get_battery_level(&mut adc, &mut pin);
-
-        sender
-            .publish(Event::StatsUpdated(Stats {
-                battery_level: level,
-                heap_used: HEAP.used(),
-                heap_free: HEAP.free(),
-            }))
-            .await;
-        Timer::after(Duration::from_secs(30)).await;
+        let power = gauge.power();
+        let reading = gauge.update(read_battery_mv(&mut adc, &mut pin), Instant::now());
+
+        // plugging in shows up right away
+        if reads % PUBLISH_EVERY == 0 || reading.power != power {
+            log::info!("Battery: {:?}", reading);
+            sender
+                .publish(Event::StatsUpdated(Stats {
+                    battery_level: reading.level,
+                    battery_mv: reading.mv,
+                    power: reading.power,
+                    minutes_left: reading.minutes_left,
+                    heap_used: HEAP.used(),
+                    heap_free: HEAP.free(),
+                }))
+                .await;
+        }
+        reads += 1;
+        Timer::after(READ_EVERY).await;
     }
 }
 
@@ -92,6 +146,10 @@ async fn main(spawner: Spawner) -> ! {
 
     // esp_println::logger::init_logger_from_env();
     logger::init();
+    // PINs and session tokens, only needed once the network is up
+    app::auth::init(stick::random_bytes);
+    #[cfg(feature = "syslog")]
+    logger::enable_remote_sink(stick::syslog::BUFFER_CAPACITY);
 
     let config = esp_hal::Config::default().with_cpu_clock(CpuClock::max());
     let peripherals = esp_hal::init(config);
@@ -101,30 +159,171 @@ async fn main(spawner: Spawner) -> ! {
     let timg0 = TimerGroup::new(peripherals.TIMG0);
     esp_rtos::start(timg0.timer0);
 
-    #[cfg(feature = "now")]
-    {
-        use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
-        use esp_radio::{
-            Controller,
-            esp_now::{EspNowManager, EspNowSender},
-            wifi::WifiController,
-        };
+    let output_config = OutputConfig::default();
+    let button_config = InputConfig::default().with_pull(Pull::Up);
+
+    // keeps the stick on when it runs from the battery
+    let mut power = Output::new(peripherals.GPIO4, Level::High, output_config);
+    let mut backlight = stick::backlight::Backlight::new(peripherals.LEDC, peripherals.GPIO27);
+
+    // back from deep sleep, the pins above held their levels until now
+    let (wake, snapshot) = stick::sleep::wake();
+    log::info!("wake: {:?}", wake);
+    if wake == Wake::Timer {
+        backlight.set(0);
+    }
+    #[cfg(feature = "mqtt")]
+    if wake == Wake::Timer {
+        spawner
+            .spawn(stick::sleep::telemetry_task(
+                snapshot.clone().unwrap_or_default(),
+            ))
+            .unwrap();
+    }
+
+    let mut display = {
+        let mut delay = Delay::new();
+
+        let dc = Output::new(peripherals.GPIO14, Level::Low, output_config);
+
+        let mut rst = Output::new(peripherals.GPIO12, Level::Low, output_config);
+        rst.set_high();
+
+        let spi = Spi::new(
+            peripherals.SPI2,
+            Config::default().with_frequency(Rate::from_mhz(40)),
+        )
+        .unwrap()
+        .with_sck(peripherals.GPIO13)
+        .with_mosi(peripherals.GPIO15);
+
+        let cs_output = Output::new(peripherals.GPIO5, Level::High, output_config);
+        let spi_device = ExclusiveDevice::new_no_delay(spi, cs_output).unwrap();
 
-        let radio_controller = mk_static!(Controller<'static>, esp_radio::init().unwrap());
+        let buffer = Box::leak(Box::new([0_u8; 512]));
+        let di = SpiInterface::new(spi_device, dc, buffer);
 
-        let wifi = peripherals.WIFI;
-        let (mut controller, interfaces) =
-            esp_radio::wifi::new(radio_controller, wifi, Default::default()).unwrap();
+        let mut display = mipidsi::Builder::new(mipidsi::models::ST7789, di)
+            .display_size(135, 240)
+            .display_offset(52, 40)
+            .invert_colors(ColorInversion::Inverted)
+            .orientation(Orientation::new().rotate(Rotation::Deg270))
+            .reset_pin(rst)
+            .init(&mut delay)
+            .unwrap();
 
-        controller.set_mode(esp_radio::wifi::WifiMode::Sta).unwrap();
-        controller.start().unwrap();
+        display.clear(Rgb565::BLACK).unwrap();
 
-        // > Dropping the controller will deinitialize / stop Wi-Fi.
-        // !!!
-        mk_static!(WifiController<'static>, controller);
+        display
+    };
 
-        let esp_now = interfaces.esp_now;
-        esp_now.set_channel(11).unwrap();
+    let backend = EmbeddedBackend::new(
+        &mut display,
+        EmbeddedBackendConfig {
+            // the panel sleeps while the app is idle, see `stick::idle`
+            flush_callback: Box::new(|display| {
+                let Some(asleep) = stick::idle::panel_change() else {
+                    return;
+                };
+                let mut delay = Delay::new();
+                let result = if asleep {
+                    display.sleep(&mut delay)
+                } else {
+                    display.wake(&mut delay)
+                };
+                match result {
+                    Ok(()) => stick::idle::set_panel_asleep(asleep),
+                    Err(e) => log::warn!("display: can't change sleep mode: {:?}", e),
+                }
+            }),
+            ..Default::default()
+        },
+    );
+
+    let mut terminal = Terminal::new(backend).unwrap();
+
+    // A woke us up, that's no reason for the menu
+    let a_held =
+        wake == Wake::Boot && Input::new(peripherals.GPIO37.reborrow(), button_config).is_low();
+    let mut buttons = Buttons::new(
+        events::sender(),
+        Input::new(peripherals.GPIO37, button_config),
+        Input::new(peripherals.GPIO39, button_config),
+        Input::new(peripherals.GPIO35, button_config),
+    );
+    // the press that woke the stick, or the A held for the role menu, does nothing
+    if wake == Wake::Button || a_held {
+        buttons.ignore_held();
+    }
+
+    spawner
+        .spawn(buttons_task(buttons, Rtc::new(peripherals.LPWR)))
+        .unwrap();
+    spawner
+        .spawn(stick::backlight::backlight_task(
+            events::receiver(),
+            backlight,
+        ))
+        .unwrap();
+
+    // a stored role is used right away, holding A at boot brings the menu back
+    stick::storage::init(peripherals.FLASH);
+    let mut settings = stick::storage::load();
+    let role = match settings.role {
+        Some(role) if !a_held => role,
+        current => {
+            let mut receiver = events::receiver();
+            let role = role::select_role(&mut terminal, &mut receiver, current)
+                .await
+                .unwrap();
+            settings.role = Some(role);
+            if let Err(e) = stick::storage::save(&settings) {
+                log::warn!("storage: can't save settings: {:?}", e);
+            }
+            role
+        }
+    };
+    log::info!("Starting as {}", role);
+    // after a power off, the flash has what was on screen
+    let snapshot = snapshot.or(settings.snapshot.take());
+
+    // every radio user takes its interface first, then the radio starts in the mode covering them all
+    #[cfg(any(
+        feature = "now",
+        feature = "server",
+        feature = "client",
+        feature = "scan",
+        feature = "monitor"
+    ))]
+    let mut radio = stick::radio::Radio::new(peripherals.WIFI);
+    #[cfg(feature = "server")]
+    let ap_device = radio.ap(stick::server::SSID);
+    #[cfg(feature = "client")]
+    let sta_device = radio.sta(stick::client::SSID, stick::client::PASSWORD);
+    // pages go to every role, drive commands only between the controller and the vehicle
+    #[cfg(feature = "now")]
+    let esp_now = radio.esp_now();
+    #[cfg(feature = "scan")]
+    radio.scan(events::sender(), events::receiver());
+    #[cfg(feature = "monitor")]
+    radio.monitor(events::sender());
+    #[cfg(any(
+        feature = "now",
+        feature = "server",
+        feature = "client",
+        feature = "scan",
+        feature = "monitor"
+    ))]
+    radio.start(spawner).await;
+
+    #[cfg(feature = "now")]
+    {
+        use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+        use esp_radio::esp_now::{EspNowManager, EspNowSender};
+
+        // with a STA, ESP-NOW follows the channel of the joined network
+        #[cfg(not(feature = "client"))]
+        esp_now.set_channel(stick::radio::CHANNEL).unwrap();
 
         log::info!("esp-now version {}", esp_now.version().unwrap());
 
@@ -138,16 +337,14 @@ async fn main(spawner: Spawner) -> ! {
 
         spawner
             .spawn(stick::now::listener(
-                EVENTS.publisher().unwrap(),
+                events::sender(),
                 manager,
                 receiver,
+                role,
             ))
             .ok();
         spawner
-            .spawn(stick::now::broadcaster(
-                EVENTS.subscriber().unwrap(),
-                sender,
-            ))
+            .spawn(stick::now::broadcaster(events::receiver(), sender, role))
             .ok();
     }
 
@@ -156,29 +353,11 @@ async fn main(spawner: Spawner) -> ! {
         use core::{net::Ipv4Addr, str::FromStr};
         use embassy_net::StackResources;
         use esp_hal::rng::Rng;
-        use esp_radio::{
-            Controller,
-            wifi::{AccessPointConfig, ModeConfig},
-        };
         use stick::server;
 
-        let radio_controller = mk_static!(Controller<'static>, esp_radio::init().unwrap());
-
         let gw_ip_addr_str = "192.168.2.1";
 
-        let (mut controller, interfaces) =
-            esp_radio::wifi::new(radio_controller, peripherals.WIFI, Default::default()).unwrap();
-
-        controller.set_mode(esp_radio::wifi::WifiMode::Ap).unwrap();
-        controller
-            .set_config(&ModeConfig::AccessPoint(
-                AccessPointConfig::default().with_ssid("stick".into()),
-            ))
-            .unwrap();
-        controller.start_async().await.unwrap();
-        embassy_time::Timer::after(embassy_time::Duration::from_millis(100)).await;
-
-        let device = interfaces.ap;
+        let device = ap_device;
 
         let gw_ip_addr = Ipv4Addr::from_str(gw_ip_addr_str).expect("failed to parse gateway ip");
 
@@ -194,64 +373,98 @@ async fn main(spawner: Spawner) -> ! {
         let (stack, runner) = embassy_net::new(
             device,
             config,
-            mk_static!(StackResources<3>, StackResources::<3>::new()),
+            // DHCP, the RPC, syslog and discovery
+            mk_static!(StackResources<4>, StackResources::<4>::new()),
             seed,
         );
 
-        spawner.spawn(server::connection(controller)).unwrap();
         spawner.spawn(server::net_task(runner)).unwrap();
+        // with a STA as well, logs go out over the joined network
+        #[cfg(all(feature = "syslog", not(feature = "client")))]
+        spawner
+            .spawn(stick::syslog::syslog_task(
+                stack,
+                stick::syslog::COLLECTOR.parse().unwrap(),
+            ))
+            .unwrap();
+        #[cfg(not(feature = "client"))]
+        spawner
+            .spawn(stick::radio::status_task(stack, events::sender()))
+            .unwrap();
+        // with a STA as well, the sticks to find are on the joined network
+        #[cfg(all(feature = "discovery", not(feature = "client")))]
         spawner
-            .spawn(server::run_dhcp(stack, gw_ip_addr_str))
+            .spawn(stick::discovery::discovery_task(
+                stack,
+                events::sender(),
+                events::receiver(),
+                role,
+            ))
+            .unwrap();
+        spawner
+            .spawn(server::run_dhcp(
+                stack,
+                gw_ip_addr_str,
+                events::sender(),
+                events::receiver(),
+            ))
             .unwrap();
-        spawner.spawn(server::echo_server(stack)).unwrap();
 
-        loop {
-            if stack.is_link_up() {
-                break;
+        #[cfg(feature = "ota")]
+        let mut ota_flash = stick::ota::EspOtaFlash::new()
+            .inspect_err(|e| log::error!("OTA: no OTA partitions: {:?}", e))
+            .ok();
+
+        let link_up = embassy_time::with_timeout(embassy_time::Duration::from_secs(30), async {
+            loop {
+                if stack.is_link_up() {
+                    break;
+                }
+                embassy_time::Timer::after(embassy_time::Duration::from_millis(500)).await;
             }
-            embassy_time::Timer::after(embassy_time::Duration::from_millis(500)).await;
+        })
+        .await;
+
+        // a fresh image that can't bring the network up can't be updated again, roll it back
+        #[cfg(feature = "ota")]
+        if let Some(flash) = &mut ota_flash {
+            match link_up {
+                Ok(()) => flash.confirm(),
+                Err(_) if flash.pending_verify() => flash.rollback(),
+                Err(_) => {}
+            }
+        }
+        if link_up.is_err() {
+            log::error!("AP link didn't come up");
         }
 
-        log::info!("AP running, UDP echo at {gw_ip_addr}:9000");
+        let handler = app::rpc::RpcHandler::new("stick");
+        #[cfg(feature = "ota")]
+        let handler = match ota_flash {
+            Some(flash) => handler.with_ota(flash),
+            None => handler,
+        };
+        spawner
+            .spawn(server::rpc_server(
+                stack,
+                handler,
+                events::sender(),
+                events::receiver(),
+            ))
+            .unwrap();
+
+        log::info!("AP running, UDP RPC at {gw_ip_addr}:{}", server::RPC_PORT);
     }
 
     #[cfg(feature = "client")]
     {
         use embassy_net::StackResources;
         use esp_hal::rng::Rng;
-        use esp_radio::Controller;
-        use esp_radio::wifi::{AuthMethod, ClientConfig, ModeConfig};
         use stick::client;
 
-        let radio_controller = mk_static!(Controller<'static>, esp_radio::init().unwrap());
-
-        let (mut controller, interfaces) =
-            esp_radio::wifi::new(radio_controller, peripherals.WIFI, Default::default()).unwrap();
-
-        controller.set_mode(esp_radio::wifi::WifiMode::Sta).unwrap();
-        let client_config = ModeConfig::Client(
-            ClientConfig::default()
-                .with_ssid("stick".into())
-                .with_auth_method(AuthMethod::None),
-        );
-        controller.set_config(&client_config).unwrap();
-        controller.start_async().await.unwrap();
-
-        log::info!("Connecting to AP 'stick'...");
-        loop {
-            match controller.connect_async().await {
-                Ok(_) => {
-                    log::info!("Connected to AP");
-                    break;
-                }
-                Err(e) => {
-                    log::warn!("Connection failed: {:?}, retrying...", e);
-                    Timer::after(Duration::from_secs(1)).await;
-                }
-            }
-        }
+        log::info!("Connecting to AP '{}'...", client::SSID);
 
-        let device = interfaces.sta;
+        let device = sta_device;
 
         let config = embassy_net::Config::dhcpv4(Default::default());
 
@@ -261,12 +474,53 @@ async fn main(spawner: Spawner) -> ! {
         let (stack, runner) = embassy_net::new(
             device,
             config,
-            mk_static!(StackResources<3>, StackResources::<3>::new()),
+            mk_static!(StackResources<9>, StackResources::<9>::new()),
             seed,
         );
 
         spawner.spawn(client::net_task(runner)).unwrap();
-        spawner.spawn(client::connection_task(controller)).unwrap();
+        spawner
+            .spawn(stick::radio::status_task(stack, events::sender()))
+            .unwrap();
+        #[cfg(feature = "syslog")]
+        spawner
+            .spawn(stick::syslog::syslog_task(
+                stack,
+                stick::syslog::COLLECTOR.parse().unwrap(),
+            ))
+            .unwrap();
+        #[cfg(feature = "sntp")]
+        {
+            use stick::sntp;
+
+            match app::clock::parse_utc_offset(sntp::UTC_OFFSET) {
+                Some(offset) => app::clock::set_utc_offset(offset),
+                None => log::warn!("Invalid UTC_OFFSET '{}'", sntp::UTC_OFFSET),
+            }
+            spawner.spawn(sntp::sntp_task(stack)).unwrap();
+        }
+        #[cfg(feature = "mqtt")]
+        spawner
+            .spawn(stick::mqtt::mqtt_task(
+                stack,
+                stick::mqtt::BROKER.parse().unwrap(),
+                events::receiver(),
+                events::sender(),
+            ))
+            .unwrap();
+        #[cfg(feature = "dashboard")]
+        spawner
+            .spawn(stick::dashboard::dashboard_task(stack, events::sender()))
+            .unwrap();
+        #[cfg(feature = "discovery")]
+        spawner
+            .spawn(stick::discovery::discovery_task(
+                stack,
+                events::sender(),
+                events::receiver(),
+                role,
+            ))
+            .unwrap();
 
         loop {
             if stack.is_link_up() {
@@ -275,128 +529,62 @@ async fn main(spawner: Spawner) -> ! {
             embassy_time::Timer::after(embassy_time::Duration::from_millis(500)).await;
         }
 
-        spawner.spawn(client::echo_client(stack)).unwrap();
+        spawner
+            .spawn(client::ping_client(stack, events::sender()))
+            .unwrap();
     }
 
     let mut adc_config = AdcConfig::new();
     let battery_pin = adc_config.enable_pin(peripherals.GPIO38, Attenuation::_11dB);
     let adc = Adc::new(peripherals.ADC1, adc_config);
     spawner
-        .spawn(stats_task(adc, battery_pin, EVENTS.publisher().unwrap()))
+        .spawn(stats_task(adc, battery_pin, events::sender()))
         .unwrap();
 
-    let output_config = OutputConfig::default();
-    let button_config = InputConfig::default().with_pull(Pull::Up);
-
-    let _power = Output::new(peripherals.GPIO4, Level::High, output_config);
-
-    let mut display = {
-        let mut delay = Delay::new();
-
-        let dc = Output::new(peripherals.GPIO14, Level::Low, output_config);
-
-        let mut rst = Output::new(peripherals.GPIO12, Level::Low, output_config);
-        rst.set_high();
-
-        let spi = Spi::new(
-            peripherals.SPI2,
-            Config::default().with_frequency(Rate::from_mhz(40)),
-        )
-        .unwrap()
-        .with_sck(peripherals.GPIO13)
-        .with_mosi(peripherals.GPIO15);
-
-        let cs_output = Output::new(peripherals.GPIO5, Level::High, output_config);
-        let spi_device = ExclusiveDevice::new_no_delay(spi, cs_output).unwrap();
-
-        let buffer = Box::leak(Box::new([0_u8; 512]));
-        let di = SpiInterface::new(spi_device, dc, buffer);
-
-        let mut display = mipidsi::Builder::new(mipidsi::models::ST7789, di)
-            .display_size(135, 240)
-            .display_offset(52, 40)
-            .invert_colors(ColorInversion::Inverted)
-            .orientation(Orientation::new().rotate(Rotation::Deg270))
-            .reset_pin(rst)
-            .init(&mut delay)
-            .unwrap();
-
-        display.clear(Rgb565::BLACK).unwrap();
-
-        display
-    };
-
-    let backend = EmbeddedBackend::new(
-        &mut display,
-        EmbeddedBackendConfig {
-            ..Default::default()
-        },
-    );
-
-    let mut terminal = Terminal::new(backend).unwrap();
-
-    let buttons = Buttons::new(
-        EVENTS.publisher().unwrap(),
-        Input::new(peripherals.GPIO37, button_config),
-        Input::new(peripherals.GPIO39, button_config),
-        Input::new(peripherals.GPIO35, button_config),
-    );
-
-    let mut app = App::new();
-
-    spawner.spawn(buttons_task(buttons)).unwrap();
-
-    #[cfg(not(feature = "vehicle"))]
-    {
-        let i2c = I2c::new(
-            peripherals.I2C0,
-            esp_hal::i2c::master::Config::default()
-                .with_frequency(Rate::from_khz(100))
-                .with_software_timeout(esp_hal::i2c::master::SoftwareTimeout::PerByte(
-                    esp_hal::time::Duration::from_millis(10),
-                )),
-        )
-        .unwrap()
-        .with_sda(peripherals.GPIO0)
-        .with_scl(peripherals.GPIO26)
-        .into_async();
-
-        let mut joyc = MiniJoyC::new(i2c, EVENTS.publisher().unwrap());
-
-        if joyc.is_connected().await {
-            spawner.spawn(minijoyc_task(joyc)).unwrap();
+    // headless, the telemetry task puts the stick back to sleep
+    if wake == Wake::Timer {
+        loop {
+            Timer::after(Duration::from_secs(1)).await;
         }
     }
 
-    #[cfg(feature = "vehicle")]
-    {
+    let i2c = I2c::new(
+        peripherals.I2C0,
+        esp_hal::i2c::master::Config::default()
+            .with_frequency(Rate::from_khz(100))
+            .with_software_timeout(esp_hal::i2c::master::SoftwareTimeout::PerByte(
+                esp_hal::time::Duration::from_millis(10),
+            )),
+    )
+    .unwrap()
+    .with_sda(peripherals.GPIO0)
+    .with_scl(peripherals.GPIO26)
+    .into_async();
+
+    // the vehicle wears the 8Servos hat, everything else can take a MiniJoyC
+    if role == Role::Vehicle {
         use stick::vehicle::{Hat8Servos, vehicle_task};
 
-        let i2c = I2c::new(
-            peripherals.I2C0,
-            esp_hal::i2c::master::Config::default()
-                .with_frequency(Rate::from_khz(100))
-                .with_software_timeout(esp_hal::i2c::master::SoftwareTimeout::PerByte(
-                    esp_hal::time::Duration::from_millis(10),
-                )),
-        )
-        .unwrap()
-        .with_sda(peripherals.GPIO0)
-        .with_scl(peripherals.GPIO26)
-        .into_async();
-
         let mut servos = Hat8Servos::new(i2c);
 
         if servos.is_connected().await {
             log::info!("Hat 8Servos v1.1 connected");
+            events::sender()
+                .publish(Event::InitHat(StickHat::Hat8Servos))
+                .await;
             spawner
-                .spawn(vehicle_task(EVENTS.subscriber().unwrap(), servos))
+                .spawn(vehicle_task(events::receiver(), servos))
                 .unwrap();
         }
+    } else {
+        let mut joyc = MiniJoyC::new(i2c, events::sender());
+
+        if joyc.is_connected().await {
+            spawner.spawn(minijoyc_task(joyc)).unwrap();
+        }
     }
 
-    #[cfg(feature = "ir")]
-    {
+    if role == Role::Remote {
         use esp_hal::rmt::{Rmt, TxChannelCreator};
         use stick::ir;
 
@@ -410,13 +598,40 @@ async fn main(spawner: Spawner) -> ! {
             .unwrap();
 
         spawner
-            .spawn(ir::tx_task(EVENTS.subscriber().unwrap(), ir_tx_channel))
+            .spawn(ir::tx_task(
+                events::receiver(),
+                events::sender(),
+                ir_tx_channel,
+            ))
             .unwrap();
     }
 
-    let _backlight = Output::new(peripherals.GPIO27, Level::High, output_config);
+    // with `deep-sleep` the remote sleeps between uses and comes back where it was
+    let mut app = App::new(role).with_battery_limits(stick::battery::limits());
+    if cfg!(feature = "deep-sleep") && role == Role::Remote {
+        app = app.with_deep_sleep();
+    }
+    if let Some(snapshot) = &snapshot {
+        app = app.with_snapshot(snapshot);
+    }
+
+    if app.run(&mut terminal).await.unwrap() == Exit::DeepSleep {
+        // with a radio, MQTT says goodbye first, without one the buttons task would light-sleep
+        if !stick::idle::LIGHT_SLEEP {
+            Timer::after(Duration::from_millis(500)).await;
+        }
+        stick::sleep::deep_sleep(&app.snapshot());
+    }
 
-    app.run(&mut terminal).await.unwrap();
+    // `Event::PowerOff` went out, give the tasks a moment to save what they have
+    Timer::after(Duration::from_millis(500)).await;
+    settings.snapshot = Some(app.snapshot());
+    if let Err(e) = stick::storage::save(&settings) {
+        log::warn!("storage: can't save settings: {:?}", e);
+    }
+    log::info!("power off");
+    power.set_low();
 
-    loop {}
+    // on USB the goodbye screen stays up, the other tasks keep running
+    match core::future::pending::<core::convert::Infallible>().await {}
 }
@@ -1,4 +1,11 @@
-use esp_hal::gpio::Input;
+use esp_hal::{
+    gpio::{Input, RtcPin},
+    peripherals::{GPIO35, GPIO37, GPIO39},
+    rtc_cntl::{
+        Rtc,
+        sleep::{RtcioWakeupSource, WakeupLevel},
+    },
+};
 use log::trace;
 
 use app::events::{self, Event, Sender};
@@ -70,6 +77,27 @@ impl Buttons {
             self.sender.publish(Event::Draw).await;
         }
     }
+
+    /// Buttons down right now only report their release, the press was before we were up.
+    pub fn ignore_held(&mut self) {
+        for button in [&mut self.a, &mut self.b, &mut self.c] {
+            button.prev_state = button.is_pressed();
+        }
+    }
+
+    /// Light-sleeps until A, B or C goes down, the next [`Self::update`] sees the press.
+    pub fn sleep_until_pressed(&mut self, rtc: &mut Rtc<'_>) {
+        // SAFETY: the inputs keep their pins, the wakeup only needs their RTC side
+        let (mut a, mut b, mut c) = unsafe { (GPIO37::steal(), GPIO39::steal(), GPIO35::steal()) };
+        let mut pins: [(&mut dyn RtcPin, WakeupLevel); 3] = [
+            (&mut a, WakeupLevel::Low),
+            (&mut b, WakeupLevel::Low),
+            (&mut c, WakeupLevel::Low),
+        ];
+        trace!("light sleep");
+        rtc.sleep_light(&[&RtcioWakeupSource::new(&mut pins)]);
+        trace!("woke up");
+    }
 }
 
 pub struct Button<'a> {
@@ -1,22 +1,47 @@
 use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 
+use app::{
+    Event, Sender,
+    rpc::{self, Authorization, Request, Response},
+};
 use edge_nal::UdpBind;
 use edge_nal_embassy::{Udp, UdpBuffers};
+use embassy_futures::select::{Either, select};
 use embassy_net::{Runner, Stack};
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 
-use esp_radio::wifi::{WifiController, WifiDevice, WifiEvent, WifiStaState};
+use esp_radio::wifi::WifiDevice;
 
-pub const RPC_PORT: u16 = 9000;
+use crate::netstat;
+
+pub use app::rpc::RPC_PORT;
 pub const SERVER_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 2, 1);
 
+/// Network to join, override with `WIFI_SSID`/`WIFI_PASSWORD` at build time.
+/// Defaults to the open AP of a stick running the `server` feature.
+pub const SSID: &str = match option_env!("WIFI_SSID") {
+    Some(ssid) => ssid,
+    None => "stick",
+};
+pub const PASSWORD: Option<&str> = option_env!("WIFI_PASSWORD");
+
+const PING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Pings the server stick, and takes pages from the LAN on [`RPC_PORT`].
+///
+/// Pages go out with `publish().await`, that can't deadlock: this task doesn't subscribe, so
+/// the bus only waits for the other subscribers to catch up.
 #[embassy_executor::task]
-pub async fn echo_client(stack: Stack<'static>) {
-    let buffers: UdpBuffers<3, 512, 512, 3> = UdpBuffers::new();
+pub async fn ping_client(stack: Stack<'static>, sender: Sender) {
+    let buffers: UdpBuffers<3, { rpc::MAX_PACKET_SIZE }, { rpc::MAX_PACKET_SIZE }, 3> =
+        UdpBuffers::new();
 
     let socket = Udp::new(stack, &buffers);
     let mut socket = match socket
-        .bind(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)))
+        .bind(SocketAddr::V4(SocketAddrV4::new(
+            Ipv4Addr::UNSPECIFIED,
+            RPC_PORT,
+        )))
         .await
     {
         Ok(s) => s,
@@ -27,58 +52,118 @@ pub async fn echo_client(stack: Stack<'static>) {
     };
 
     let server = SocketAddr::V4(SocketAddrV4::new(SERVER_IP, RPC_PORT));
-    let mut buf = [0u8; 256];
+    let mut buf = [0u8; rpc::MAX_PACKET_SIZE];
+    let mut out = [0u8; rpc::MAX_PACKET_SIZE];
+    let mut ping_buf = [0u8; 8];
+    let ping = rpc::encode(&Request::Ping, &mut ping_buf).unwrap();
+    let mut ping_at = Instant::now();
+    let mut awaiting_pong = false;
 
     loop {
-        log::debug!("sending hello...");
-        if let Err(e) = edge_nal::UdpSend::send(&mut socket, server, b"hello").await {
-            log::warn!("send error: {:?}", e);
-        } else {
-            log::debug!("sent, waiting for response...");
-            match embassy_time::with_timeout(
-                Duration::from_secs(2),
-                edge_nal::UdpReceive::receive(&mut socket, &mut buf),
-            )
-            .await
-            {
-                Ok(Ok((len, from))) => {
-                    let msg = core::str::from_utf8(&buf[..len]).unwrap_or("<invalid utf8>");
-                    log::info!("recv from {}: {}", from, msg);
+        let received = match select(
+            edge_nal::UdpReceive::receive(&mut socket, &mut buf),
+            Timer::at(ping_at),
+        )
+        .await
+        {
+            Either::First(received) => received,
+            Either::Second(()) => {
+                if awaiting_pong {
+                    log::warn!("recv timeout");
                 }
-                Ok(Err(e)) => {
-                    log::warn!("recv error: {:?}", e);
+                log::debug!("sending ping...");
+                match edge_nal::UdpSend::send(&mut socket, server, ping).await {
+                    Ok(()) => {
+                        netstat::udp_sent();
+                        awaiting_pong = true;
+                    }
+                    Err(e) => log::warn!("send error: {:?}", e),
                 }
-                Err(_) => {
-                    log::warn!("recv timeout");
+                ping_at = Instant::now() + PING_INTERVAL;
+                continue;
+            }
+        };
+
+        let (len, from) = match received {
+            Ok(received) => received,
+            Err(e) => {
+                log::warn!("recv error: {:?}", e);
+                Timer::after(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        netstat::udp_received();
+
+        if from == server {
+            awaiting_pong = false;
+            match rpc::decode::<Response>(&buf[..len]) {
+                Ok(Response::Pong { name, version }) => {
+                    log::info!("pong from {}: {} v{}", from, name, version);
                 }
+                Ok(response) => log::info!("recv from {}: {:?}", from, response),
+                Err(e) => log::warn!("bad response from {}: {:?}", from, e),
             }
+            continue;
         }
 
-        Timer::after(Duration::from_secs(1)).await;
+        // anyone else is a host with a page, or pairing to send one
+        let response = match rpc::decode::<Request>(&buf[..len]).map(rpc::authenticate) {
+            Ok(Authorization::Granted(Request::Page { page, relay })) => {
+                log::info!("page from {}", page.from);
+                sender
+                    .publish(if relay {
+                        Event::SendPage(page)
+                    } else {
+                        Event::Page(page)
+                    })
+                    .await;
+                Response::Ok
+            }
+            Ok(Authorization::Granted(_)) => {
+                Response::Error("only pages here, ask the server stick".into())
+            }
+            Ok(Authorization::Answered(response, event)) => {
+                if let Some(event) = event {
+                    sender.publish(event).await;
+                }
+                response
+            }
+            Err(e) => {
+                log::warn!("bad request from {}: {:?}", from, e);
+                Response::Error("bad request".into())
+            }
+        };
+        if let Ok(msg) = rpc::encode(&response, &mut out) {
+            match edge_nal::UdpSend::send(&mut socket, from, msg).await {
+                Ok(()) => netstat::udp_sent(),
+                Err(e) => log::warn!("send error: {:?}", e),
+            }
+        }
     }
 }
 
-#[embassy_executor::task]
-pub async fn net_task(mut runner: Runner<'static, WifiDevice<'static>>) {
-    runner.run().await
-}
+/// `host` as an IPv4 address, looked up if it isn't one.
+#[cfg(any(feature = "sntp", feature = "dashboard"))]
+pub async fn resolve(stack: Stack<'static>, host: &str) -> Option<Ipv4Addr> {
+    use embassy_net::{IpAddress, dns::DnsQueryType};
 
-#[embassy_executor::task]
-pub async fn connection_task(mut controller: WifiController<'static>) {
-    log::debug!("connection_task started");
-    loop {
-        match esp_radio::wifi::sta_state() {
-            WifiStaState::Connected => {
-                controller.wait_for_event(WifiEvent::StaDisconnected).await;
-                log::warn!("WiFi disconnected, reconnecting...");
-                Timer::after(Duration::from_millis(1000)).await;
-            }
-            _ => {
-                if let Err(e) = controller.connect_async().await {
-                    log::warn!("reconnect failed: {:?}", e);
-                    Timer::after(Duration::from_millis(1000)).await;
-                }
-            }
+    if let Ok(ip) = host.parse() {
+        return Some(ip);
+    }
+    match stack.dns_query(host, DnsQueryType::A).await {
+        Ok(addrs) => addrs.iter().find_map(|addr| match addr {
+            IpAddress::Ipv4(ip) => Some(*ip),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }),
+        Err(e) => {
+            log::warn!("can't resolve {}: {:?}", host, e);
+            None
         }
     }
 }
+
+#[embassy_executor::task]
+pub async fn net_task(mut runner: Runner<'static, WifiDevice<'static>>) {
+    runner.run().await
+}
@@ -0,0 +1,99 @@
+//! Polls a JSON endpoint for the dash tab, see [`app::dashboard`].
+
+use core::net::{IpAddr, SocketAddr};
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use app::{
+    Dashboard, Event, Sender,
+    dashboard::{self, Field, MAX_RESPONSE_SIZE, Url},
+};
+use embassy_net::{Stack, tcp::TcpSocket};
+use embassy_time::{Duration, Timer, with_timeout};
+use embedded_io_async::Write;
+
+/// `http://host[:port]/path`, override with `DASHBOARD_URL` at build time.
+pub const URL: &str = match option_env!("DASHBOARD_URL") {
+    Some(url) => url,
+    None => "http://192.168.2.2:8080/status.json",
+};
+
+/// `label=/json/pointer,...`, override with `DASHBOARD_FIELDS` at build time.
+pub const FIELDS: &str = match option_env!("DASHBOARD_FIELDS") {
+    Some(fields) => fields,
+    None => "status=/status",
+};
+
+/// Sent as a bearer token if set with `DASHBOARD_TOKEN` at build time.
+pub const TOKEN: Option<&str> = option_env!("DASHBOARD_TOKEN");
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[embassy_executor::task]
+pub async fn dashboard_task(stack: Stack<'static>, sender: Sender) {
+    let fields = dashboard::parse_fields(FIELDS);
+    log::info!("dashboard: polling {}", URL);
+
+    loop {
+        stack.wait_config_up().await;
+
+        let dashboard = match with_timeout(TIMEOUT, poll(stack, &fields)).await {
+            Ok(Ok(rows)) => Dashboard { rows, error: None },
+            Ok(Err(e)) => Dashboard {
+                rows: Vec::new(),
+                error: Some(e.to_string()),
+            },
+            Err(_) => Dashboard {
+                rows: Vec::new(),
+                error: Some("timeout".into()),
+            },
+        };
+        if let Some(error) = &dashboard.error {
+            log::warn!("dashboard: {}", error);
+        }
+        sender.publish(Event::Dashboard(dashboard)).await;
+
+        Timer::after(POLL_INTERVAL).await;
+    }
+}
+
+async fn poll(
+    stack: Stack<'static>,
+    fields: &[Field],
+) -> Result<Vec<(String, String)>, dashboard::Error> {
+    let url = Url::parse(URL)?;
+    let ip = crate::client::resolve(stack, url.host)
+        .await
+        .ok_or(dashboard::Error::Url)?;
+
+    let mut rx_buf = [0u8; 1024];
+    let mut tx_buf = [0u8; 512];
+    let mut socket = TcpSocket::new(stack, &mut rx_buf, &mut tx_buf);
+    socket
+        .connect(SocketAddr::new(IpAddr::V4(ip), url.port))
+        .await
+        .map_err(|_| dashboard::Error::Response)?;
+    socket
+        .write_all(dashboard::request(&url, TOKEN).as_bytes())
+        .await
+        .map_err(|_| dashboard::Error::Response)?;
+
+    // HTTP/1.0, the server closes the connection after the body
+    let mut response = vec![0u8; MAX_RESPONSE_SIZE];
+    let mut filled = 0;
+    while filled < response.len() {
+        match socket.read(&mut response[filled..]).await {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(_) => return Err(dashboard::Error::Response),
+        }
+    }
+    socket.close();
+
+    let body = dashboard::parse_response(&response[..filled])?;
+    dashboard::extract(body, fields)
+}
@@ -0,0 +1,97 @@
+//! Announces this stick on the LAN and fills the peer table, see [`app::discovery`].
+
+use core::convert::Infallible;
+
+use app::{
+    Event, Receiver, Role, Sender,
+    discovery::{self, ANNOUNCE_INTERVAL, Announce, GROUP, MAX_ANNOUNCE_SIZE, PORT},
+};
+use embassy_futures::select::{Either, Either3, select, select3};
+use embassy_net::{
+    IpAddress, Stack,
+    udp::{PacketMetadata, UdpSocket},
+};
+use embassy_time::Ticker;
+
+use crate::netstat;
+
+#[embassy_executor::task]
+pub async fn discovery_task(
+    stack: Stack<'static>,
+    sender: Sender,
+    mut receiver: Receiver,
+    role: Role,
+) {
+    let mut me = Announce::new(&crate::hostname(), role);
+
+    stack.wait_config_up().await;
+    if let Err(e) = stack.join_multicast_group(GROUP) {
+        log::error!("discovery: can't join {}: {:?}", GROUP, e);
+        return;
+    }
+
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buf = [0u8; 4 * MAX_ANNOUNCE_SIZE];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buf = [0u8; MAX_ANNOUNCE_SIZE];
+    let mut socket = UdpSocket::new(stack, &mut rx_meta, &mut rx_buf, &mut tx_meta, &mut tx_buf);
+    if let Err(e) = socket.bind(PORT) {
+        log::error!("discovery: can't bind port {}: {:?}", PORT, e);
+        return;
+    }
+    log::info!("discovery: announcing {} on {}:{}", me.name, GROUP, PORT);
+
+    let mut ticker = Ticker::every(ANNOUNCE_INTERVAL);
+    let mut buf = [0u8; MAX_ANNOUNCE_SIZE];
+    let mut out = [0u8; MAX_ANNOUNCE_SIZE];
+    loop {
+        match select3(
+            ticker.next(),
+            socket.recv_from(&mut buf),
+            receiver.next_message_pure(),
+        )
+        .await
+        {
+            Either3::First(()) => {
+                if let Some(len) = me.encode(&mut out) {
+                    match socket.send_to(&out[..len], (GROUP, PORT)).await {
+                        Ok(()) => netstat::udp_sent(),
+                        Err(e) => log::warn!("discovery: send failed: {:?}", e),
+                    }
+                }
+                if discovery::expire() {
+                    peers_changed(&sender, &mut receiver, &mut me).await;
+                }
+            }
+            Either3::Second(Ok((len, meta))) => {
+                netstat::udp_received();
+                let IpAddress::Ipv4(ip) = meta.endpoint.addr else {
+                    continue;
+                };
+                let Some(announce) = Announce::decode(&buf[..len]) else {
+                    continue;
+                };
+                // in case the group loops back
+                if announce.name != me.name && discovery::record(ip, announce) {
+                    peers_changed(&sender, &mut receiver, &mut me).await;
+                }
+            }
+            Either3::Second(Err(e)) => log::warn!("discovery: receive failed: {:?}", e),
+            Either3::Third(event) => me.update(&event),
+        }
+    }
+}
+
+/// Keeps reading the bus while it's full, we're one of the subscribers it waits on.
+async fn peers_changed(sender: &Sender, receiver: &mut Receiver, me: &mut Announce) {
+    let publish = sender.publish(Event::PeersChanged);
+    if let Either::Second(never) = select(publish, follow(receiver, me)).await {
+        match never {}
+    }
+}
+
+async fn follow(receiver: &mut Receiver, me: &mut Announce) -> Infallible {
+    loop {
+        me.update(&receiver.next_message_pure().await);
+    }
+}
@@ -0,0 +1,33 @@
+//! The panel and the CPU while [`app::idle`] says the screen is off.
+//!
+//! The panel goes to sleep (ST7789 sleep-in) on the next flushed frame, after that the
+//! buttons task light-sleeps until a button goes down.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use app::idle::{self, IdleState};
+
+/// The radio needs the CPU, with it on only the screen goes off.
+pub const LIGHT_SLEEP: bool = !cfg!(any(
+    feature = "now",
+    feature = "server",
+    feature = "client",
+    feature = "scan",
+    feature = "monitor"
+));
+
+static PANEL_ASLEEP: AtomicBool = AtomicBool::new(false);
+
+pub fn panel_asleep() -> bool {
+    PANEL_ASLEEP.load(Ordering::Relaxed)
+}
+
+/// Where the panel should be, `Some(asleep)` when it isn't there yet.
+pub fn panel_change() -> Option<bool> {
+    let off = idle::state() == IdleState::Off;
+    (off != panel_asleep()).then_some(off)
+}
+
+pub fn set_panel_asleep(asleep: bool) {
+    PANEL_ASLEEP.store(asleep, Ordering::Relaxed);
+}
@@ -1,4 +1,5 @@
-use app::events::{self, Receiver};
+use alloc::format;
+use app::events::{self, Receiver, Sender};
 use embassy_time::{Duration, Timer};
 use esp_hal::{
     gpio::Level,
@@ -8,6 +9,7 @@ use esp_hal::{
 #[embassy_executor::task]
 pub async fn tx_task(
     mut receiver: Receiver,
+    sender: Sender,
     mut ir_tx_channel: esp_hal::rmt::Channel<'static, esp_hal::Async, esp_hal::rmt::Tx>,
 ) {
     log::info!("📡 IR Transmitter ready on GPIO19");
@@ -35,7 +37,17 @@ pub async fn tx_task(
             let pulses = encode_nec_command(0x04, cmd);
 
             match ir_tx_channel.transmit(&pulses).await {
-                Ok(_) => log::debug!("IR signal sent successfully"),
+                Ok(_) => {
+                    log::debug!("IR signal sent successfully");
+                    let name: &str = remote_event.into();
+                    // we're a subscriber too, waiting on a full bus would wait on ourselves
+                    if sender
+                        .try_publish(events::Event::Toast(format!("IR {}", name)))
+                        .is_err()
+                    {
+                        log::debug!("IR: bus full, toast skipped");
+                    }
+                }
                 Err(e) => log::error!("IR transmit failed: {:?}", e),
             }
         }
@@ -2,11 +2,30 @@
 
 extern crate alloc;
 
+use alloc::{format, string::String};
+
+pub mod backlight;
 pub mod battery;
 pub mod button;
-
-#[cfg(feature = "ir")]
+pub mod idle;
 pub mod ir;
+pub mod minijoyc;
+pub mod netstat;
+pub mod sleep;
+pub mod storage;
+pub mod vehicle;
+
+#[cfg(any(
+    feature = "now",
+    feature = "server",
+    feature = "client",
+    feature = "scan",
+    feature = "monitor"
+))]
+pub mod radio;
+
+#[cfg(feature = "monitor")]
+pub mod monitor;
 
 #[cfg(feature = "server")]
 pub mod server;
@@ -14,10 +33,37 @@ pub mod server;
 #[cfg(feature = "client")]
 pub mod client;
 
+#[cfg(feature = "ota")]
+pub mod ota;
+
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+
+#[cfg(feature = "sntp")]
+pub mod sntp;
+
+#[cfg(feature = "syslog")]
+pub mod syslog;
+
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+
+#[cfg(feature = "discovery")]
+pub mod discovery;
+
 #[cfg(feature = "now")]
 pub mod now;
 
-#[cfg(feature = "vehicle")]
-pub mod vehicle;
+/// Fills `buf` from the hardware RNG, random once the radio is on.
+pub fn random_bytes(buf: &mut [u8]) {
+    let rng = esp_hal::rng::Rng::new();
+    for chunk in buf.chunks_mut(4) {
+        chunk.copy_from_slice(&rng.random().to_le_bytes()[..chunk.len()]);
+    }
+}
 
-pub mod minijoyc;
+/// `stick-xxxxxx`, the last three bytes of the MAC.
+pub fn hostname() -> String {
+    let mac = esp_hal::efuse::Efuse::mac_address();
+    format!("stick-{:02x}{:02x}{:02x}", mac[3], mac[4], mac[5])
+}
@@ -0,0 +1,75 @@
+//! Promiscuous-mode sniffer feeding [`app::monitor::Monitor`].
+//!
+//! The sniffer hears whatever channel the radio is on. With nothing else using the radio
+//! it hops through all of them instead, so the per-channel counts cover the whole band.
+
+use core::cell::RefCell;
+
+use app::{Event, Sender, monitor::Monitor};
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embassy_time::{Duration, Ticker};
+use esp_radio::{
+    esp_now::EspNow,
+    wifi::{PromiscuousPkt, Sniffer},
+};
+
+/// Time on each channel while hopping.
+const DWELL: Duration = Duration::from_millis(250);
+const STATS_INTERVAL: u32 = (1000 / DWELL.as_millis()) as u32;
+
+static MONITOR: Mutex<CriticalSectionRawMutex, RefCell<Monitor>> =
+    Mutex::new(RefCell::new(Monitor::new()));
+
+/// Runs in the Wi-Fi driver, keep it short.
+fn on_frame(packet: PromiscuousPkt<'_>) {
+    MONITOR.lock(|monitor| {
+        monitor.borrow_mut().record(
+            packet.rx_cntl.channel as u8,
+            packet.rx_cntl.rssi as i8,
+            packet.data,
+        )
+    });
+}
+
+/// Publishes `Event::PacketStats` every second. `hop` is the idle ESP-NOW interface, only
+/// used to switch channels.
+#[embassy_executor::task]
+pub async fn monitor_task(
+    mut sniffer: Sniffer<'static>,
+    sender: Sender,
+    hop: Option<EspNow<'static>>,
+) {
+    sniffer.set_receive_cb(on_frame);
+    if let Err(e) = sniffer.set_promiscuous_mode(true) {
+        log::error!("monitor: can't enable promiscuous mode: {:?}", e);
+        return;
+    }
+    log::info!(
+        "monitor: sniffing {}",
+        if hop.is_some() {
+            "all channels"
+        } else {
+            "the radio's channel"
+        }
+    );
+
+    let mut ticker = Ticker::every(DWELL);
+    let mut channel = 1;
+    let mut ticks = 0;
+    loop {
+        ticker.next().await;
+
+        if let Some(esp_now) = &hop {
+            channel = channel % 13 + 1;
+            if let Err(e) = esp_now.set_channel(channel) {
+                log::warn!("monitor: can't switch to channel {}: {:?}", channel, e);
+            }
+        }
+
+        ticks += 1;
+        if ticks % STATS_INTERVAL == 0 {
+            let stats = MONITOR.lock(|monitor| monitor.borrow_mut().take_stats());
+            sender.publish(Event::PacketStats(stats)).await;
+        }
+    }
+}
@@ -0,0 +1,411 @@
+//! Minimal MQTT 3.1.1 client: QoS 0 publish/subscribe over an embassy-net TCP socket.
+//!
+//! Topic and payload mapping lives in [`app::mqtt`].
+
+use core::{convert::Infallible, net::SocketAddr};
+
+use alloc::{format, vec::Vec};
+#[cfg(feature = "homeassistant")]
+use app::ha;
+use app::{
+    Event, Receiver, Sender, Stats,
+    mqtt::{self, COMMAND_TOPICS, OFFLINE, ONLINE, STATUS_TOPIC},
+};
+use embassy_futures::select::{Either, Either3, select, select3};
+use embassy_net::{
+    Stack,
+    tcp::{self, ConnectError, TcpSocket},
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
+use embedded_io_async::Write;
+
+/// Broker address, override with `MQTT_BROKER=ip:port` at build time.
+pub const BROKER: &str = match option_env!("MQTT_BROKER") {
+    Some(addr) => addr,
+    None => "192.168.2.2:1883",
+};
+
+const KEEP_ALIVE_SECS: u16 = 30;
+
+/// Signaled whenever [`Event::StatsUpdated`] went out, a telemetry wakeup waits for it.
+pub static STATS_SENT: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+const BUFFER_SIZE: usize = 1024;
+
+#[derive(Debug)]
+pub enum Error {
+    Connect(ConnectError),
+    Tcp(tcp::Error),
+    /// CONNACK return code.
+    Refused(u8),
+    Protocol,
+    Timeout,
+}
+
+impl From<tcp::Error> for Error {
+    fn from(e: tcp::Error) -> Self {
+        Self::Tcp(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum Packet<'a> {
+    ConnAck { code: u8 },
+    Publish { topic: &'a str, payload: &'a [u8] },
+    SubAck,
+    PingResp,
+    Other,
+}
+
+fn put_remaining_len(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 5);
+    out.push(header);
+    put_remaining_len(&mut out, body.len());
+    out.extend_from_slice(body);
+    out
+}
+
+/// CONNECT with a clean session and a retained last will.
+pub fn connect(client_id: &str, will_topic: &str, will_payload: &str, keep_alive: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_str(&mut body, "MQTT");
+    body.push(4); // protocol level 3.1.1
+    body.push(0x02 | 0x04 | 0x20); // clean session, will flag, will retain
+    body.extend_from_slice(&keep_alive.to_be_bytes());
+    put_str(&mut body, client_id);
+    put_str(&mut body, will_topic);
+    put_str(&mut body, will_payload);
+    packet(0x10, &body)
+}
+
+/// PUBLISH with QoS 0.
+pub fn publish(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    put_str(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(0x30 | retain as u8, &body)
+}
+
+/// SUBSCRIBE to a single topic filter with QoS 0.
+pub fn subscribe(packet_id: u16, filter: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&packet_id.to_be_bytes());
+    put_str(&mut body, filter);
+    body.push(0);
+    packet(0x82, &body)
+}
+
+pub const PINGREQ: [u8; 2] = [0xC0, 0x00];
+
+/// Parses one packet from the start of `buf`.
+///
+/// Returns the packet and the number of bytes it took, or `None` if `buf` holds only part of it.
+pub fn parse(buf: &[u8]) -> Result<Option<(Packet<'_>, usize)>, Error> {
+    let Some(&header) = buf.first() else {
+        return Ok(None);
+    };
+
+    let mut len = 0usize;
+    let mut offset = 1;
+    loop {
+        let Some(&byte) = buf.get(offset) else {
+            return Ok(None);
+        };
+        len |= ((byte & 0x7F) as usize) << (7 * (offset - 1));
+        offset += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if offset > 4 {
+            return Err(Error::Protocol);
+        }
+    }
+
+    let Some(body) = buf.get(offset..offset + len) else {
+        return Ok(None);
+    };
+
+    let packet = match header >> 4 {
+        2 => Packet::ConnAck {
+            code: *body.get(1).ok_or(Error::Protocol)?,
+        },
+        3 => {
+            let qos = (header >> 1) & 0x03;
+            let topic_len = u16::from_be_bytes([
+                *body.first().ok_or(Error::Protocol)?,
+                *body.get(1).ok_or(Error::Protocol)?,
+            ]) as usize;
+            let topic = body.get(2..2 + topic_len).ok_or(Error::Protocol)?;
+            let topic = core::str::from_utf8(topic).map_err(|_| Error::Protocol)?;
+            // QoS 1/2 messages carry a packet id, we only subscribe with QoS 0 though
+            let payload_start = 2 + topic_len + if qos > 0 { 2 } else { 0 };
+            Packet::Publish {
+                topic,
+                payload: body.get(payload_start..).ok_or(Error::Protocol)?,
+            }
+        }
+        9 => Packet::SubAck,
+        13 => Packet::PingResp,
+        _ => Packet::Other,
+    };
+
+    Ok(Some((packet, offset + len)))
+}
+
+/// Keeps an MQTT session up: publishes telemetry from app events and turns command
+/// messages into app events.
+#[embassy_executor::task]
+pub async fn mqtt_task(
+    stack: Stack<'static>,
+    broker: SocketAddr,
+    mut receiver: Receiver,
+    sender: Sender,
+) {
+    let client_id = crate::hostname();
+    let base = format!("stick/{}", client_id);
+    // the newest stats from while there was no session, they go out first thing
+    let mut stats = None;
+
+    loop {
+        if let Either::Second(never) = select(
+            stack.wait_config_up(),
+            keep_latest(&mut receiver, &mut stats),
+        )
+        .await
+        {
+            match never {}
+        }
+
+        match session(
+            stack,
+            broker,
+            &client_id,
+            &base,
+            &mut receiver,
+            &mut stats,
+            &sender,
+        )
+        .await
+        {
+            Ok(()) => log::warn!("MQTT disconnected, reconnecting..."),
+            Err(e) => log::warn!("MQTT session failed: {:?}, retrying...", e),
+        }
+
+        if let Either::Second(never) = select(
+            Timer::after(Duration::from_millis(1000)),
+            keep_latest(&mut receiver, &mut stats),
+        )
+        .await
+        {
+            match never {}
+        }
+    }
+}
+
+/// Reads the bus while there's no session, nobody waits on us that way.
+async fn keep_latest(receiver: &mut Receiver, stats: &mut Option<Stats>) -> Infallible {
+    loop {
+        if let Event::StatsUpdated(latest) = receiver.next_message_pure().await {
+            *stats = Some(latest);
+        }
+    }
+}
+
+/// Telemetry for `event`, if it is any.
+async fn publish_telemetry(
+    socket: &mut TcpSocket<'_>,
+    base: &str,
+    event: &Event,
+) -> Result<(), Error> {
+    let Some((topic, payload)) = mqtt::telemetry(event) else {
+        return Ok(());
+    };
+    let topic = format!("{}/{}", base, topic);
+    socket
+        .write_all(&publish(&topic, payload.as_bytes(), false))
+        .await?;
+    if matches!(event, Event::StatsUpdated(_)) {
+        socket.flush().await?;
+        STATS_SENT.signal(());
+    }
+    Ok(())
+}
+
+async fn session(
+    stack: Stack<'static>,
+    broker: SocketAddr,
+    client_id: &str,
+    base: &str,
+    receiver: &mut Receiver,
+    stats: &mut Option<Stats>,
+    sender: &Sender,
+) -> Result<(), Error> {
+    let mut rx_buf = [0u8; BUFFER_SIZE];
+    let mut tx_buf = [0u8; BUFFER_SIZE];
+    let mut socket = TcpSocket::new(stack, &mut rx_buf, &mut tx_buf);
+    socket.set_timeout(Some(Duration::from_secs(KEEP_ALIVE_SECS as u64 * 2)));
+
+    let status_topic = format!("{}/{}", base, STATUS_TOPIC);
+    let mut buf = [0u8; BUFFER_SIZE];
+    let mut filled = 0;
+
+    // the bus keeps moving until the broker answered
+    let handshake = async {
+        socket.connect(broker).await.map_err(Error::Connect)?;
+        socket
+            .write_all(&connect(client_id, &status_topic, OFFLINE, KEEP_ALIVE_SECS))
+            .await?;
+        with_timeout(Duration::from_secs(5), async {
+            loop {
+                let n = socket.read(&mut buf[filled..]).await?;
+                if n == 0 {
+                    return Err(Error::Protocol);
+                }
+                filled += n;
+                if let Some((packet, used)) = parse(&buf[..filled])? {
+                    let Packet::ConnAck { code } = packet else {
+                        return Err(Error::Protocol);
+                    };
+                    buf.copy_within(used..filled, 0);
+                    filled -= used;
+                    return Ok(code);
+                }
+            }
+        })
+        .await
+        .map_err(|_| Error::Timeout)?
+    };
+    let code = match select(handshake, keep_latest(receiver, stats)).await {
+        Either::First(code) => code?,
+        Either::Second(never) => match never {},
+    };
+
+    if code != 0 {
+        return Err(Error::Refused(code));
+    }
+
+    socket
+        .write_all(&publish(&status_topic, ONLINE.as_bytes(), true))
+        .await?;
+    socket
+        .write_all(&subscribe(1, &format!("{}/{}", base, COMMAND_TOPICS)))
+        .await?;
+
+    #[cfg(feature = "homeassistant")]
+    {
+        socket.write_all(&subscribe(2, ha::HA_STATUS_TOPIC)).await?;
+        publish_discovery(&mut socket, client_id, base).await?;
+    }
+
+    log::info!("MQTT connected to {} as {}", broker, client_id);
+
+    if let Some(stats) = stats.take() {
+        publish_telemetry(&mut socket, base, &Event::StatsUpdated(stats)).await?;
+    }
+
+    let ping_interval = Duration::from_secs(KEEP_ALIVE_SECS as u64 / 2);
+    let mut next_ping = Instant::now() + ping_interval;
+
+    loop {
+        if filled == buf.len() {
+            log::warn!("MQTT packet too large");
+            return Err(Error::Protocol);
+        }
+
+        match select3(
+            socket.read(&mut buf[filled..]),
+            receiver.next_message_pure(),
+            Timer::at(next_ping),
+        )
+        .await
+        {
+            Either3::First(read) => {
+                let n = read?;
+                if n == 0 {
+                    return Ok(());
+                }
+                filled += n;
+
+                while let Some((packet, used)) = parse(&buf[..filled])? {
+                    #[cfg(feature = "homeassistant")]
+                    let ha_online = matches!(
+                        packet,
+                        Packet::Publish { topic: ha::HA_STATUS_TOPIC, payload } if payload == ONLINE.as_bytes()
+                    );
+                    let event = match packet {
+                        Packet::Publish { topic, payload } => topic
+                            .strip_prefix(base)
+                            .and_then(|topic| topic.strip_prefix('/'))
+                            .and_then(|topic| {
+                                log::debug!("MQTT command {}", topic);
+                                mqtt::command(topic, payload)
+                            }),
+                        _ => None,
+                    };
+                    buf.copy_within(used..filled, 0);
+                    filled -= used;
+
+                    // we're a subscriber too, so a full bus drops the command like QoS 0 may
+                    if let Some(event) = event
+                        && sender.try_publish(event).is_err()
+                    {
+                        log::debug!("MQTT: bus full, command dropped");
+                    }
+
+                    #[cfg(feature = "homeassistant")]
+                    if ha_online {
+                        publish_discovery(&mut socket, client_id, base).await?;
+                    }
+                }
+            }
+            Either3::Second(Event::PowerOff | Event::DeepSleep) => {
+                // the will only fires once the broker gives up on us
+                socket
+                    .write_all(&publish(&status_topic, OFFLINE.as_bytes(), true))
+                    .await?;
+                socket.flush().await?;
+            }
+            Either3::Second(event) => {
+                publish_telemetry(&mut socket, base, &event).await?;
+            }
+            Either3::Third(()) => {
+                socket.write_all(&PINGREQ).await?;
+                next_ping = Instant::now() + ping_interval;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "homeassistant")]
+async fn publish_discovery(
+    socket: &mut TcpSocket<'_>,
+    client_id: &str,
+    base: &str,
+) -> Result<(), Error> {
+    for (topic, payload) in ha::discovery_configs(client_id, base) {
+        socket
+            .write_all(&publish(&topic, payload.as_bytes(), true))
+            .await?;
+    }
+    log::info!("MQTT published Home Assistant discovery");
+    Ok(())
+}
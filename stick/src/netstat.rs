@@ -0,0 +1,14 @@
+//! UDP packet counters for `Event::NetStatus`, bumped by every task that owns a UDP socket.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub static UDP_RX: AtomicU32 = AtomicU32::new(0);
+pub static UDP_TX: AtomicU32 = AtomicU32::new(0);
+
+pub fn udp_received() {
+    UDP_RX.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn udp_sent() {
+    UDP_TX.fetch_add(1, Ordering::Relaxed);
+}
@@ -1,50 +1,58 @@
-use app::{Receiver, Sender};
+use app::{Controller, Event, Receiver, Role, Sender, Vehicle, pager::Page};
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
 use esp_radio::esp_now::{
     BROADCAST_ADDRESS, EspNowManager, EspNowReceiver, EspNowSender, PeerInfo,
 };
+use serde::{Deserialize, Serialize};
 
+/// ESP-NOW payload limit.
+const MAX_MESSAGE_SIZE: usize = 250;
+
+/// What goes over the air, postcard-encoded.
+#[derive(Debug, Serialize, Deserialize)]
+enum Message {
+    Controller(Controller),
+    Page(Page),
+}
+
+/// Broadcasts drive commands in the controller role, and pages in any role.
 #[embassy_executor::task]
 pub async fn broadcaster(
     mut app_receiver: Receiver,
-    #[allow(unused)] now_sender: &'static Mutex<CriticalSectionRawMutex, EspNowSender<'static>>,
+    now_sender: &'static Mutex<CriticalSectionRawMutex, EspNowSender<'static>>,
+    role: Role,
 ) {
-    #[allow(unused)]
-    let mut buf = [0u8; 8];
+    let mut buf = [0u8; MAX_MESSAGE_SIZE];
     loop {
-        #[allow(unused)]
         let evt = app_receiver.next_message_pure().await;
 
-        #[cfg(feature = "controller")]
-        {
-            use app::Event;
-
-            let msg = match evt {
-                Event::Controller(controller) => Some(postcard::to_slice(&controller, &mut buf)),
-                _ => None,
-            };
-
-            let Some(msg) = msg else {
-                continue;
-            };
+        let message = match evt {
+            Event::Controller(controller) if role == Role::Controller => {
+                Message::Controller(controller)
+            }
+            Event::SendPage(page) => Message::Page(page),
+            _ => continue,
+        };
 
-            match msg {
-                Ok(msg) => {
-                    let mut sender = now_sender.lock().await;
-                    let status = sender.send_async(&BROADCAST_ADDRESS, msg).await;
-                    log::info!("Broadcast {:?}: {:?}", evt, status);
-                }
-                Err(_) => {}
+        match postcard::to_slice(&message, &mut buf) {
+            Ok(msg) => {
+                let mut sender = now_sender.lock().await;
+                let status = sender.send_async(&BROADCAST_ADDRESS, msg).await;
+                log::info!("Broadcast {:?}: {:?}", message, status);
             }
+            Err(e) => log::warn!("Can't encode {:?}: {:?}", message, e),
         }
     }
 }
 
+/// Tracks peers, turns drive commands into [`Vehicle`] events in the vehicle role and
+/// passes pages on in any role.
 #[embassy_executor::task]
 pub async fn listener(
-    #[allow(unused)] app_sender: Sender,
+    app_sender: Sender,
     manager: &'static EspNowManager<'static>,
     mut receiver: EspNowReceiver<'static>,
+    role: Role,
 ) {
     loop {
         log::debug!("Received message");
@@ -64,20 +72,19 @@ pub async fn listener(
             }
         }
 
-        #[cfg(feature = "vehicle")]
-        {
-            use app::{Controller, Event, Vehicle};
-
-            let controller_msg = postcard::from_bytes::<Controller>(msg.data());
-            match controller_msg {
-                Ok(Controller::Move(left, right)) => {
+        match postcard::from_bytes::<Message>(msg.data()) {
+            Ok(Message::Controller(Controller::Move(left, right))) => {
+                if role == Role::Vehicle {
                     app_sender
                         .publish(Event::Vehicle(Vehicle::Move(left, right)))
                         .await;
                 }
-                Err(err) => {
-                    log::error!("Parse error: {:?}", err);
-                }
+            }
+            Ok(Message::Page(page)) => {
+                app_sender.publish(Event::Page(page)).await;
+            }
+            Err(err) => {
+                log::error!("Parse error: {:?}", err);
             }
         }
     }
@@ -0,0 +1,142 @@
+//! [`OtaFlash`] on the ESP-IDF OTA partitions.
+//!
+//! Needs a partition table with `otadata`, `ota_0` and `ota_1` (see `partitions.csv`) and a
+//! bootloader built with `CONFIG_BOOTLOADER_APP_ROLLBACK_ENABLE`, so an image that never
+//! calls [`EspOtaFlash::confirm`] is rolled back on the next reset.
+//!
+//! The flash itself is shared with the settings, see [`crate::storage::with_flash`].
+
+use alloc::vec::Vec;
+
+use app::ota::{OtaError, OtaFlash};
+use embedded_storage::{ReadStorage, nor_flash::NorFlash};
+use esp_bootloader_esp_idf::{
+    ota::OtaImageState,
+    ota_updater::OtaUpdater,
+    partitions::{self, PARTITION_TABLE_MAX_LEN},
+};
+use esp_storage::FlashStorage;
+
+use crate::storage::with_flash;
+
+const SECTOR_SIZE: u32 = 4096;
+const WRITE_ALIGN: usize = 4;
+
+pub struct EspOtaFlash {
+    buffer: [u8; PARTITION_TABLE_MAX_LEN],
+    capacity: u32,
+}
+
+impl EspOtaFlash {
+    /// Needs [`crate::storage::init`] first.
+    pub fn new() -> Result<Self, partitions::Error> {
+        let mut this = Self {
+            buffer: [0; PARTITION_TABLE_MAX_LEN],
+            capacity: 0,
+        };
+        this.capacity = with_flash(|flash| {
+            let mut ota = OtaUpdater::new(flash, &mut this.buffer)?;
+            let (next, _) = ota.next_partition()?;
+            Ok::<_, partitions::Error>(next.capacity() as u32)
+        })?;
+        Ok(this)
+    }
+
+    /// Runs `f` with the flash held, on the partition table read fresh.
+    fn with_updater<T>(
+        &mut self,
+        f: impl FnOnce(&mut OtaUpdater<'_, FlashStorage<'static>>) -> Result<T, OtaError>,
+    ) -> Result<T, OtaError> {
+        with_flash(|flash| {
+            let mut ota = OtaUpdater::new(flash, &mut self.buffer).map_err(|e| {
+                log::warn!("OTA: can't read partition table: {:?}", e);
+                OtaError::Flash
+            })?;
+            f(&mut ota)
+        })
+    }
+
+    /// `true` if this is a fresh image that hasn't been confirmed yet.
+    pub fn pending_verify(&mut self) -> bool {
+        self.with_updater(|ota| ota.current_ota_state().map_err(|_| OtaError::Flash))
+            .is_ok_and(|state| matches!(state, OtaImageState::New | OtaImageState::PendingVerify))
+    }
+
+    /// Marks the running image as good, the bootloader keeps it from now on.
+    pub fn confirm(&mut self) {
+        if !self.pending_verify() {
+            return;
+        }
+        match self.with_updater(|ota| {
+            ota.set_current_ota_state(OtaImageState::Valid)
+                .map_err(|_| OtaError::Flash)
+        }) {
+            Ok(()) => log::info!("OTA: image confirmed"),
+            Err(e) => log::error!("OTA: can't confirm image: {:?}", e),
+        }
+    }
+
+    /// Marks the running image as bad and resets into the previous one.
+    pub fn rollback(&mut self) -> ! {
+        log::error!("OTA: image failed its health check, rolling back");
+        _ = self.with_updater(|ota| {
+            ota.set_current_ota_state(OtaImageState::Invalid)
+                .map_err(|_| OtaError::Flash)
+        });
+        esp_hal::system::software_reset()
+    }
+}
+
+impl OtaFlash for EspOtaFlash {
+    fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    fn erase_size(&self) -> u32 {
+        SECTOR_SIZE
+    }
+
+    /// A sector at a time, the settings can get at the flash in between.
+    fn erase(&mut self, offset: u32, len: u32) -> Result<(), OtaError> {
+        for sector in (offset..offset + len).step_by(SECTOR_SIZE as usize) {
+            self.with_updater(|ota| {
+                let (mut next, _) = ota.next_partition().map_err(|_| OtaError::Flash)?;
+                next.erase(sector, sector + SECTOR_SIZE).map_err(|e| {
+                    log::warn!("OTA: erase at {} failed: {:?}", sector, e);
+                    OtaError::Flash
+                })
+            })?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), OtaError> {
+        self.with_updater(|ota| {
+            let (mut next, _) = ota.next_partition().map_err(|_| OtaError::Flash)?;
+
+            // only the last chunk can be unaligned, pad it with erased bytes
+            let result = if data.len() % WRITE_ALIGN == 0 {
+                next.write(offset, data)
+            } else {
+                let mut padded = Vec::from(data);
+                padded.resize(data.len().next_multiple_of(WRITE_ALIGN), 0xFF);
+                next.write(offset, &padded)
+            };
+            result.map_err(|e| {
+                log::warn!("OTA: write at {} failed: {:?}", offset, e);
+                OtaError::Flash
+            })
+        })
+    }
+
+    fn activate(&mut self) -> Result<(), OtaError> {
+        self.with_updater(|ota| {
+            ota.activate_next_partition()
+                .and_then(|_| ota.set_current_ota_state(OtaImageState::New))
+                .map_err(|e| {
+                    log::warn!("OTA: activate failed: {:?}", e);
+                    OtaError::Flash
+                })
+        })
+    }
+}
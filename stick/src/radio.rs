@@ -0,0 +1,391 @@
+//! Single owner of the Wi-Fi radio.
+//!
+//! `now`, `server` and `client` each ask [`Radio`] for their interface, then
+//! [`Radio::start`] picks the mode that covers all of them: AP, STA or AP+STA, with
+//! ESP-NOW alongside any of them. The radio has one channel, so the AP, ESP-NOW and
+//! the joined network all share it.
+//!
+//! [`Radio::scan`] answers `Event::Scan`. Scanning needs the STA interface, so it is
+//! brought up even without a network to join, and hops channels for a moment. So does
+//! [`Radio::monitor`], which keeps hopping if nothing else needs a fixed channel.
+
+use core::{cell::RefCell, convert::Infallible};
+
+use alloc::string::String;
+use app::{AccessPoint, AuthMode, Event, NetMode, Receiver, Sender};
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embassy_time::{Duration, Instant, Timer};
+use esp_hal::peripherals::WIFI;
+use esp_radio::{
+    Controller,
+    esp_now::EspNow,
+    wifi::{
+        AccessPointConfig, AccessPointInfo, AuthMethod, ClientConfig, Interfaces, ModeConfig,
+        ScanConfig, WifiController, WifiDevice, WifiEvent, WifiMode, WifiStaState,
+    },
+};
+use static_cell::StaticCell;
+
+/// Channel for the AP and ESP-NOW. With a STA, the joined network decides.
+pub const CHANNEL: u8 = 11;
+
+const RSSI_INTERVAL: Duration = Duration::from_secs(5);
+
+static RADIO: StaticCell<Controller<'static>> = StaticCell::new();
+
+/// What the connection task knows about the link, for [`status_task`].
+struct Link {
+    mode: NetMode,
+    ssid: Option<String>,
+    rssi: Option<i8>,
+    connected_since: Option<Instant>,
+    reconnects: u32,
+}
+
+static LINK: Mutex<CriticalSectionRawMutex, RefCell<Link>> = Mutex::new(RefCell::new(Link {
+    mode: NetMode::Off,
+    ssid: None,
+    rssi: None,
+    connected_since: None,
+    reconnects: 0,
+}));
+
+fn update_link(f: impl FnOnce(&mut Link)) {
+    LINK.lock(|link| f(&mut link.borrow_mut()));
+}
+
+pub struct Radio {
+    controller: WifiController<'static>,
+    ap_device: Option<WifiDevice<'static>>,
+    sta_device: Option<WifiDevice<'static>>,
+    esp_now: Option<EspNow<'static>>,
+    ap: Option<AccessPointConfig>,
+    sta: Option<ClientConfig>,
+    scanner: Option<Scanner>,
+    #[cfg(feature = "monitor")]
+    sniffer: Option<esp_radio::wifi::Sniffer<'static>>,
+    #[cfg(feature = "monitor")]
+    monitor: Option<Sender>,
+}
+
+struct Scanner {
+    sender: Sender,
+    receiver: Receiver,
+    /// A scan was asked for while the radio was busy.
+    pending: bool,
+}
+
+impl Scanner {
+    async fn requested(&mut self) {
+        if core::mem::take(&mut self.pending) {
+            return;
+        }
+        while !matches!(self.receiver.next_message_pure().await, Event::Scan) {}
+    }
+}
+
+/// Keeps reading the bus while the radio is busy, a scan request is kept for later.
+async fn note_requests(receiver: &mut Receiver, pending: &mut bool) -> Infallible {
+    loop {
+        if let Event::Scan = receiver.next_message_pure().await {
+            *pending = true;
+        }
+    }
+}
+
+impl Radio {
+    pub fn new(wifi: WIFI<'static>) -> Self {
+        let radio = RADIO.init(esp_radio::init().unwrap());
+        let (controller, interfaces) =
+            esp_radio::wifi::new(radio, wifi, Default::default()).unwrap();
+        let Interfaces {
+            ap,
+            sta,
+            esp_now,
+            #[cfg(feature = "monitor")]
+            sniffer,
+            ..
+        } = interfaces;
+        Self {
+            controller,
+            ap_device: Some(ap),
+            sta_device: Some(sta),
+            esp_now: Some(esp_now),
+            ap: None,
+            sta: None,
+            scanner: None,
+            #[cfg(feature = "monitor")]
+            sniffer: Some(sniffer),
+            #[cfg(feature = "monitor")]
+            monitor: None,
+        }
+    }
+
+    /// Runs an open access point on [`CHANNEL`].
+    pub fn ap(&mut self, ssid: &str) -> WifiDevice<'static> {
+        self.ap = Some(
+            AccessPointConfig::default()
+                .with_ssid(ssid.into())
+                .with_channel(CHANNEL),
+        );
+        update_link(|link| {
+            link.ssid.get_or_insert_with(|| ssid.into());
+        });
+        self.ap_device.take().expect("AP interface taken twice")
+    }
+
+    /// Joins a network, WPA2 if there's a password. Reconnects whenever it drops.
+    pub fn sta(&mut self, ssid: &str, password: Option<&str>) -> WifiDevice<'static> {
+        let config = ClientConfig::default().with_ssid(ssid.into());
+        self.sta = Some(match password {
+            Some(password) => config
+                .with_password(password.into())
+                .with_auth_method(AuthMethod::Wpa2Personal),
+            None => config.with_auth_method(AuthMethod::None),
+        });
+        // the joined network is the more interesting one
+        update_link(|link| link.ssid = Some(ssid.into()));
+        self.sta_device.take().expect("STA interface taken twice")
+    }
+
+    /// Set the channel after [`Radio::start`], unless there's a STA.
+    pub fn esp_now(&mut self) -> EspNow<'static> {
+        self.esp_now.take().expect("ESP-NOW interface taken twice")
+    }
+
+    /// Answers `Event::Scan` with `Event::ScanResults`.
+    pub fn scan(&mut self, sender: Sender, receiver: Receiver) {
+        self.scanner = Some(Scanner {
+            sender,
+            receiver,
+            pending: false,
+        });
+    }
+
+    /// Publishes `Event::PacketStats` from sniffed frames, see [`crate::monitor`].
+    #[cfg(feature = "monitor")]
+    pub fn monitor(&mut self, sender: Sender) {
+        self.monitor = Some(sender);
+    }
+
+    /// Starts the radio in the mode the requested interfaces need and keeps the STA connected.
+    pub async fn start(mut self, spawner: Spawner) {
+        let sta = self.sta.is_some();
+        #[cfg(feature = "monitor")]
+        let monitoring = self.monitor.is_some();
+        #[cfg(not(feature = "monitor"))]
+        let monitoring = false;
+        // nobody else pins the channel, the monitor may hop
+        #[cfg(feature = "monitor")]
+        let idle = self.ap.is_none() && !sta && self.esp_now.is_some();
+        // an unconfigured STA is enough to scan or sniff
+        let sta_config = self
+            .sta
+            .take()
+            .or_else(|| (self.scanner.is_some() || monitoring).then(ClientConfig::default));
+        let (mode, config) = match (self.ap.take(), sta_config) {
+            (Some(ap), Some(sta)) => (WifiMode::ApSta, ModeConfig::ApSta(sta, ap)),
+            (Some(ap), None) => (WifiMode::Ap, ModeConfig::AccessPoint(ap)),
+            (None, Some(sta)) => (WifiMode::Sta, ModeConfig::Client(sta)),
+            // nobody asked for the radio
+            (None, None) if self.esp_now.is_some() => {
+                log::info!("radio: not needed, staying off");
+                return;
+            }
+            // ESP-NOW alone still needs the radio up
+            (None, None) => (WifiMode::Sta, ModeConfig::Client(ClientConfig::default())),
+        };
+        log::info!("radio: starting in {:?} mode", mode);
+        update_link(|link| {
+            link.mode = match mode {
+                WifiMode::Ap => NetMode::Ap,
+                WifiMode::ApSta => NetMode::ApSta,
+                _ => NetMode::Sta,
+            }
+        });
+
+        self.controller.set_mode(mode).unwrap();
+        self.controller.set_config(&config).unwrap();
+        self.controller.start_async().await.unwrap();
+
+        #[cfg(feature = "monitor")]
+        if let Some(sender) = self.monitor.take() {
+            let sniffer = self.sniffer.take().unwrap();
+            let hop = if idle { self.esp_now.take() } else { None };
+            spawner
+                .spawn(crate::monitor::monitor_task(sniffer, sender, hop))
+                .unwrap();
+        }
+        // > Dropping the controller will deinitialize / stop Wi-Fi.
+        // the task owns it from here on
+        spawner
+            .spawn(connection_task(self.controller, sta, self.scanner))
+            .unwrap();
+    }
+}
+
+#[embassy_executor::task]
+async fn connection_task(
+    mut controller: WifiController<'static>,
+    sta: bool,
+    mut scanner: Option<Scanner>,
+) {
+    log::debug!(
+        "connection_task started. Device capabilities: {:?}",
+        controller.capabilities()
+    );
+    let mut connected_before = false;
+    loop {
+        // a scan would cancel the connect, a request meanwhile waits for it
+        if sta && !matches!(esp_radio::wifi::sta_state(), WifiStaState::Connected) {
+            let connecting = connect(&mut controller, &mut connected_before);
+            match &mut scanner {
+                Some(Scanner {
+                    receiver, pending, ..
+                }) => {
+                    if let Either::Second(never) =
+                        select(connecting, note_requests(receiver, pending)).await
+                    {
+                        match never {}
+                    }
+                }
+                None => connecting.await,
+            }
+            continue;
+        }
+
+        let link = async {
+            if sta {
+                watch_link(&mut controller).await
+            } else {
+                // nothing to reconnect, only keep the controller alive
+                core::future::pending().await
+            }
+        };
+        let scan_requested = async {
+            match &mut scanner {
+                Some(scanner) => scanner.requested().await,
+                None => core::future::pending().await,
+            }
+        };
+        let step = select(link, scan_requested).await;
+
+        if let (Either::Second(()), Some(scanner)) = (step, &mut scanner) {
+            let Scanner {
+                sender,
+                receiver,
+                pending,
+            } = scanner;
+            let scan = controller.scan_with_config_async(ScanConfig::default());
+            let found = match select(scan, note_requests(receiver, pending)).await {
+                Either::First(found) => found,
+                Either::Second(never) => match never {},
+            };
+            let access_points = match found {
+                Ok(found) => found.iter().map(access_point).collect(),
+                Err(e) => {
+                    log::warn!("scan failed: {:?}", e);
+                    Default::default()
+                }
+            };
+            let publish = sender.publish(Event::ScanResults(access_points));
+            if let Either::Second(never) = select(publish, note_requests(receiver, pending)).await {
+                match never {}
+            }
+            // whoever asked during the scan gets these results
+            *pending = false;
+        }
+    }
+}
+
+/// Joins the network, with a pause after a failed attempt.
+async fn connect(controller: &mut WifiController<'static>, connected_before: &mut bool) {
+    match controller.connect_async().await {
+        Ok(()) => update_link(|link| {
+            link.connected_since = Some(Instant::now());
+            if *connected_before {
+                link.reconnects += 1;
+            }
+            *connected_before = true;
+        }),
+        Err(e) => {
+            log::warn!("reconnect failed: {:?}", e);
+            Timer::after(Duration::from_millis(1000)).await;
+        }
+    }
+}
+
+/// Waits a while for the STA to drop, updating the RSSI if it didn't. Safe to cancel.
+async fn watch_link(controller: &mut WifiController<'static>) {
+    if let Either::First(_) = select(
+        controller.wait_for_event(WifiEvent::StaDisconnected),
+        Timer::after(RSSI_INTERVAL),
+    )
+    .await
+    {
+        log::warn!("WiFi disconnected, reconnecting...");
+        update_link(|link| {
+            link.rssi = None;
+            link.connected_since = None;
+        });
+        Timer::after(Duration::from_millis(1000)).await;
+        return;
+    }
+    let rssi = controller.rssi().ok().map(|rssi| rssi as i8);
+    update_link(|link| link.rssi = rssi);
+}
+
+fn access_point(info: &AccessPointInfo) -> AccessPoint {
+    AccessPoint {
+        ssid: info.ssid.as_str().into(),
+        bssid: info.bssid,
+        channel: info.channel,
+        rssi: info.signal_strength,
+        auth: match info.auth_method {
+            None | Some(AuthMethod::None) => AuthMode::Open,
+            Some(AuthMethod::Wep) => AuthMode::Wep,
+            Some(AuthMethod::Wpa) => AuthMode::Wpa,
+            Some(AuthMethod::Wpa2Personal) => AuthMode::Wpa2,
+            Some(AuthMethod::WpaWpa2Personal) => AuthMode::WpaWpa2,
+            Some(AuthMethod::Wpa2Wpa3Personal) => AuthMode::Wpa2Wpa3,
+            Some(AuthMethod::Wpa3Personal) => AuthMode::Wpa3,
+            Some(AuthMethod::Wpa2Enterprise) => AuthMode::Enterprise,
+            Some(_) => AuthMode::Other,
+        },
+    }
+}
+
+/// Publishes [`app::Event::NetStatus`] for the link and `stack`'s address.
+#[cfg(any(feature = "server", feature = "client"))]
+#[embassy_executor::task]
+pub async fn status_task(stack: embassy_net::Stack<'static>, sender: Sender) {
+    use core::sync::atomic::Ordering;
+
+    use app::NetStatus;
+
+    use crate::netstat::{UDP_RX, UDP_TX};
+
+    loop {
+        let config = stack.config_v4();
+        let status = LINK.lock(|link| {
+            let link = link.borrow();
+            NetStatus {
+                mode: link.mode,
+                ssid: link.ssid.clone(),
+                ip: config.as_ref().map(|config| config.address.address()),
+                gateway: config.as_ref().and_then(|config| config.gateway),
+                rssi: link.rssi,
+                connected_for: link
+                    .connected_since
+                    .map(|since| since.elapsed().as_secs() as u32),
+                reconnects: link.reconnects,
+                udp_rx: UDP_RX.load(Ordering::Relaxed),
+                udp_tx: UDP_TX.load(Ordering::Relaxed),
+            }
+        });
+        sender.publish(Event::NetStatus(status)).await;
+        Timer::after(Duration::from_secs(2)).await;
+    }
+}
@@ -1,19 +1,37 @@
+use core::convert::Infallible;
 use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use core::str::FromStr;
 
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use app::{
+    DhcpLease, Event, Receiver, Sender,
+    rpc::{self, Request, Response, RpcHandler},
+};
 use edge_nal::UdpBind;
 use edge_nal_embassy::{Udp, UdpBuffers};
+use embassy_futures::select::{Either, Either3, select, select3};
 use embassy_net::{Runner, Stack};
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Ticker, Timer};
+
+use esp_radio::wifi::WifiDevice;
 
-use esp_radio::wifi::{WifiApState, WifiController, WifiDevice, WifiEvent};
+use crate::netstat;
 
-pub const RPC_PORT: u16 = 9000;
+pub use app::rpc::RPC_PORT;
 pub const SERVER_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 2, 1);
+/// The AP is open.
+pub const SSID: &str = "stick";
 
 #[embassy_executor::task]
-pub async fn echo_server(stack: Stack<'static>) {
-    let buffers: UdpBuffers<3, 512, 512, 3> = UdpBuffers::new();
+pub async fn rpc_server(
+    stack: Stack<'static>,
+    mut handler: RpcHandler,
+    sender: Sender,
+    mut receiver: Receiver,
+) {
+    let buffers: UdpBuffers<3, { rpc::MAX_PACKET_SIZE }, { rpc::MAX_PACKET_SIZE }, 3> =
+        UdpBuffers::new();
     let udp = Udp::new(stack, &buffers);
 
     let mut socket = match udp
@@ -30,17 +48,65 @@ pub async fn echo_server(stack: Stack<'static>) {
         }
     };
 
-    log::info!("UDP echo server listening on port {}", RPC_PORT);
+    log::info!("UDP RPC server listening on port {}", RPC_PORT);
 
-    let mut buf = [0u8; 256];
+    let mut buf = [0u8; rpc::MAX_PACKET_SIZE];
+    let mut out = [0u8; rpc::MAX_PACKET_SIZE];
 
     loop {
-        match edge_nal::UdpReceive::receive(&mut socket, &mut buf).await {
+        let received = match select(
+            edge_nal::UdpReceive::receive(&mut socket, &mut buf),
+            receiver.next_message_pure(),
+        )
+        .await
+        {
+            Either::First(received) => received,
+            Either::Second(event) => {
+                handler.update(&event);
+                continue;
+            }
+        };
+
+        match received {
             Ok((len, remote)) => {
                 log::debug!("recv {} bytes from {}", len, remote);
+                netstat::udp_received();
+
+                let response = match rpc::decode::<Request>(&buf[..len]) {
+                    Ok(request) => {
+                        let (response, event) = handler.handle(request);
+                        if let Some(event) = event {
+                            // keep reading while the bus is full, or we'd wait on ourselves
+                            let publish = sender.publish(event);
+                            if let Either::Second(never) =
+                                select(publish, follow(&mut receiver, &mut handler)).await
+                            {
+                                match never {}
+                            }
+                        }
+                        response
+                    }
+                    Err(e) => {
+                        log::warn!("bad request from {}: {:?}", remote, e);
+                        Response::Error("bad request".into())
+                    }
+                };
+
+                let Ok(msg) = rpc::encode(&response, &mut out) else {
+                    log::warn!("response too large");
+                    continue;
+                };
+
+                match edge_nal::UdpSend::send(&mut socket, remote, msg).await {
+                    Ok(()) => netstat::udp_sent(),
+                    Err(e) => log::warn!("send error: {:?}", e),
+                }
 
-                if let Err(e) = edge_nal::UdpSend::send(&mut socket, remote, &buf[..len]).await {
-                    log::warn!("send error: {:?}", e);
+                if handler.ota_done() {
+                    log::info!("OTA: rebooting into the new image");
+                    // let the response get out
+                    Timer::after(Duration::from_millis(500)).await;
+                    esp_hal::system::software_reset();
                 }
             }
             Err(e) => {
@@ -51,38 +117,34 @@ pub async fn echo_server(stack: Stack<'static>) {
     }
 }
 
+/// Keeps `handler` up to date with the bus until it's dropped.
+async fn follow(receiver: &mut Receiver, handler: &mut RpcHandler) -> Infallible {
+    loop {
+        handler.update(&receiver.next_message_pure().await);
+    }
+}
+
 #[embassy_executor::task]
 pub async fn net_task(mut runner: Runner<'static, WifiDevice<'static>>) {
     runner.run().await
 }
 
-#[embassy_executor::task]
-pub async fn connection(mut controller: WifiController<'static>) {
-    log::debug!(
-        "Starting wifi. Device capabilities: {:?}",
-        controller.capabilities()
-    );
-    loop {
-        match esp_radio::wifi::ap_state() {
-            WifiApState::Started => {
-                controller.wait_for_event(WifiEvent::ApStop).await;
-                Timer::after(Duration::from_millis(5000)).await
-            }
-            _ => {}
-        }
-    }
-}
+/// How often the lease table is published.
+const LEASE_REFRESH: Duration = Duration::from_secs(5);
 
 #[embassy_executor::task]
-pub async fn run_dhcp(stack: Stack<'static>, gw_ip_addr: &'static str) {
-    use core::net::{Ipv4Addr, SocketAddrV4};
-
+pub async fn run_dhcp(
+    stack: Stack<'static>,
+    gw_ip_addr: &'static str,
+    sender: Sender,
+    mut receiver: Receiver,
+) {
     use edge_dhcp::{
-        io::{self, DEFAULT_SERVER_PORT},
+        Options, Packet,
+        io::DEFAULT_SERVER_PORT,
         server::{Server, ServerOptions},
     };
-    use edge_nal::UdpBind;
-    use edge_nal_embassy::{Udp, UdpBuffers};
+    use edge_nal::{UdpReceive, UdpSend};
 
     let ip = Ipv4Addr::from_str(gw_ip_addr).expect("dhcp task failed to parse gw ip");
 
@@ -93,22 +155,100 @@ pub async fn run_dhcp(stack: Stack<'static>, gw_ip_addr: &'static str) {
     let buffers = UdpBuffers::<3, 1024, 1024, 10>::new();
     let unbound_socket = Udp::new(stack, &buffers);
     let mut bound_socket = unbound_socket
-        .bind(core::net::SocketAddr::V4(SocketAddrV4::new(
+        .bind(SocketAddr::V4(SocketAddrV4::new(
             Ipv4Addr::UNSPECIFIED,
             DEFAULT_SERVER_PORT,
         )))
         .await
         .unwrap();
 
+    let mut server = Server::<_, 64>::new_with_et(ip);
+    let options = ServerOptions::new(ip, Some(&mut gw_buf));
+    // edge-dhcp only keeps the expiry, a changed expiry means the client renewed
+    let mut seen: BTreeMap<[u8; 6], (u64, Instant)> = BTreeMap::new();
+    let mut refresh = Ticker::every(LEASE_REFRESH);
+
+    // `io::server::run` takes the server for good, this is its loop with the lease table
+    // and the bus in between requests
     loop {
-        _ = io::server::run(
-            &mut Server::<_, 64>::new_with_et(ip),
-            &ServerOptions::new(ip, Some(&mut gw_buf)),
-            &mut bound_socket,
-            &mut buf,
-        )
-        .await
-        .inspect_err(|e| log::warn!("DHCP server error: {e:?}"));
-        Timer::after(Duration::from_millis(500)).await;
+        let kick = async {
+            loop {
+                if let Event::KickClient(mac) = receiver.next_message_pure().await {
+                    break mac;
+                }
+            }
+        };
+
+        // receiving is cancel-safe, a datagram stays queued until it's read
+        match select3(bound_socket.receive(&mut buf), refresh.next(), kick).await {
+            Either3::First(Ok((len, remote))) => {
+                let request = match Packet::decode(&buf[..len]) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        log::warn!("DHCP: bad request: {e:?}");
+                        continue;
+                    }
+                };
+                let mut opt_buf = Options::buf();
+                let Some(reply) = server.handle_request(&mut opt_buf, &options, &request) else {
+                    continue;
+                };
+                let remote = match remote {
+                    SocketAddr::V4(addr) if request.broadcast || addr.ip().is_unspecified() => {
+                        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::BROADCAST, addr.port()))
+                    }
+                    remote => remote,
+                };
+                match reply.encode(&mut buf) {
+                    Ok(reply) => {
+                        if let Err(e) = bound_socket.send(remote, reply).await {
+                            log::warn!("DHCP send error: {e:?}");
+                        }
+                    }
+                    Err(e) => log::warn!("DHCP: can't encode reply: {e:?}"),
+                }
+                continue;
+            }
+            Either3::First(Err(e)) => {
+                log::warn!("DHCP receive error: {e:?}");
+                continue;
+            }
+            Either3::Second(()) => {}
+            Either3::Third(mac) => {
+                server.leases.retain(|_, lease| lease.mac[..6] != mac);
+                log::info!("DHCP: kicked {:02x?}", mac);
+            }
+        }
+
+        let now = Instant::now();
+        let now_secs = now.as_secs();
+        let leases = server
+            .leases
+            .iter()
+            .map(|(ip, lease)| {
+                let mac: [u8; 6] = lease.mac[..6].try_into().unwrap();
+                let (_, last_seen) = seen
+                    .entry(mac)
+                    .and_modify(|(expires, last_seen)| {
+                        if *expires != lease.expires {
+                            *expires = lease.expires;
+                            *last_seen = now;
+                        }
+                    })
+                    .or_insert((lease.expires, now));
+                DhcpLease {
+                    mac,
+                    ip: *ip,
+                    expires_in: lease.expires.saturating_sub(now_secs) as u32,
+                    last_seen: (now - *last_seen).as_secs() as u32,
+                }
+            })
+            .collect::<Vec<_>>();
+        seen.retain(|mac, _| leases.iter().any(|lease| lease.mac == *mac));
+
+        // a full bus skips a refresh rather than stall the server
+        if sender.try_publish(Event::DhcpLeases(leases)).is_err() {
+            log::debug!("DHCP: bus full, lease table skipped");
+        }
     }
 }
@@ -0,0 +1,121 @@
+//! Deep sleep for the remote with the `deep-sleep` feature, see [`app::sleep`].
+//!
+//! The [`Snapshot`] waits in RTC fast memory, A wakes the stick and so does a timer when
+//! [`TELEMETRY_INTERVAL`] is set. The power latch on GPIO4 and the dark backlight on GPIO27
+//! are held through the sleep, [`wake`] lets go of them once `main` drives them again.
+
+use core::time::Duration;
+
+use app::sleep::{MAX_RECORD_SIZE, Snapshot};
+use esp_hal::{
+    gpio::RtcPin,
+    peripherals::{GPIO4, GPIO27, GPIO37, LPWR},
+    rtc_cntl::{
+        Rtc, SleepSource,
+        sleep::{Ext0WakeupSource, TimerWakeupSource, WakeupLevel},
+        wakeup_cause,
+    },
+};
+
+/// Seconds between wakeups that only report telemetry, override with
+/// `TELEMETRY_INTERVAL=secs` at build time. Unset, only A wakes the stick.
+#[cfg(feature = "mqtt")]
+pub const TELEMETRY_INTERVAL: Option<&str> = option_env!("TELEMETRY_INTERVAL");
+/// Upper bound of a telemetry wakeup, in case the network or the broker isn't there.
+#[cfg(feature = "mqtt")]
+const TELEMETRY_WINDOW: embassy_time::Duration = embassy_time::Duration::from_secs(15);
+
+#[esp_hal::ram(unstable(rtc_fast, persistent))]
+static mut SNAPSHOT: [u8; MAX_RECORD_SIZE] = [0; MAX_RECORD_SIZE];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wake {
+    /// Powered on or reset, nothing to come back to.
+    Boot,
+    /// A woke the stick, it's still down or was just released.
+    Button,
+    /// Time to report telemetry, the screen stays dark.
+    Timer,
+}
+
+/// Why the stick is up, and what it was showing before the deep sleep.
+pub fn wake() -> (Wake, Option<Snapshot>) {
+    let wake = match wakeup_cause() {
+        SleepSource::Ext0 => Wake::Button,
+        SleepSource::Timer => Wake::Timer,
+        _ => return (Wake::Boot, None),
+    };
+
+    // SAFETY: GPIO4 and GPIO27 are taken later in `main`, the holds only need their RTC side
+    let (power, backlight) = unsafe { (GPIO4::steal(), GPIO27::steal()) };
+    power.rtcio_pad_hold(false);
+    backlight.rtcio_pad_hold(false);
+
+    // SAFETY: read once at boot, before anything can go to sleep and write it
+    let record = unsafe { core::ptr::read(&raw const SNAPSHOT) };
+    (wake, Snapshot::from_record(&record))
+}
+
+/// The next wakeup that only reports telemetry, if they're on.
+pub fn telemetry_interval() -> Option<Duration> {
+    #[cfg(feature = "mqtt")]
+    if let Some(secs) = TELEMETRY_INTERVAL {
+        match secs.parse() {
+            Ok(secs) => return Some(Duration::from_secs(secs)),
+            Err(_) => log::warn!("Invalid TELEMETRY_INTERVAL '{}'", secs),
+        }
+    }
+    None
+}
+
+/// Keeps `snapshot` and sleeps until A goes down or the next telemetry is due.
+///
+/// The stick boots from scratch afterwards, see [`wake`].
+pub fn deep_sleep(snapshot: &Snapshot) -> ! {
+    let mut record = [0u8; MAX_RECORD_SIZE];
+    let bytes = snapshot.to_record();
+    match record.get_mut(..bytes.len()) {
+        Some(record) => record.copy_from_slice(&bytes),
+        None => log::warn!("sleep: snapshot too large, not kept"),
+    }
+    // SAFETY: nothing else touches it, and nothing runs after this
+    unsafe { core::ptr::write(&raw mut SNAPSHOT, record) };
+
+    // SAFETY: the CPU doesn't come back from here, whoever had the pins is done with them
+    let (power, backlight, a, lpwr) = unsafe {
+        (
+            GPIO4::steal(),
+            GPIO27::steal(),
+            GPIO37::steal(),
+            LPWR::steal(),
+        )
+    };
+    // the latch keeps the stick on while the digital side is off, the backlight stays dark
+    power.rtcio_pad_hold(true);
+    backlight.rtcio_pad_hold(true);
+
+    let mut rtc = Rtc::new(lpwr);
+    let button = Ext0WakeupSource::new(a, WakeupLevel::Low);
+    log::info!("deep sleep");
+    match telemetry_interval() {
+        Some(interval) => rtc.sleep_deep(&[&button, &TimerWakeupSource::new(interval)]),
+        None => rtc.sleep_deep(&[&button]),
+    }
+}
+
+/// Back to sleep once the stats went out over MQTT, or the window is over.
+#[cfg(feature = "mqtt")]
+#[embassy_executor::task]
+pub async fn telemetry_task(snapshot: Snapshot) {
+    use embassy_futures::select::select;
+    use embassy_time::{Duration, Timer};
+
+    select(
+        crate::mqtt::STATS_SENT.wait(),
+        Timer::after(TELEMETRY_WINDOW),
+    )
+    .await;
+    // the last packet is still on its way out
+    Timer::after(Duration::from_millis(200)).await;
+    deep_sleep(&snapshot);
+}
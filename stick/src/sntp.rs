@@ -0,0 +1,107 @@
+use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use app::clock;
+use edge_nal::UdpBind;
+use edge_nal_embassy::{Udp, UdpBuffers};
+use embassy_net::Stack;
+use embassy_time::{Duration, Instant, Timer, with_timeout};
+
+use crate::netstat;
+
+/// NTP server host name or IP, override with `NTP_SERVER` at build time.
+pub const SERVER: &str = match option_env!("NTP_SERVER") {
+    Some(server) => server,
+    None => "pool.ntp.org",
+};
+
+/// Fixed UTC offset like `+02:00`, override with `UTC_OFFSET` at build time.
+pub const UTC_OFFSET: &str = match option_env!("UTC_OFFSET") {
+    Some(offset) => offset,
+    None => "Z",
+};
+
+const NTP_PORT: u16 = 123;
+/// Seconds between 1900-01-01 and 1970-01-01.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+const RESYNC_INTERVAL: Duration = Duration::from_secs(3600);
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// SNTP v4 client request, everything but the mode is zero.
+fn request() -> [u8; 48] {
+    let mut packet = [0u8; 48];
+    packet[0] = 0b00_100_011; // LI = 0, VN = 4, mode = 3 (client)
+    packet
+}
+
+/// Unix time in ms from the transmit timestamp of a server response.
+fn parse_response(packet: &[u8]) -> Option<u64> {
+    if packet.len() < 48 {
+        return None;
+    }
+    let mode = packet[0] & 0b111;
+    let stratum = packet[1];
+    // 4 = server, stratum 0 is a kiss-o'-death
+    if mode != 4 || stratum == 0 {
+        return None;
+    }
+    let secs = u32::from_be_bytes(packet[40..44].try_into().ok()?) as u64;
+    let fraction = u32::from_be_bytes(packet[44..48].try_into().ok()?) as u64;
+    let unix_secs = secs.checked_sub(NTP_UNIX_OFFSET)?;
+    Some(unix_secs * 1000 + ((fraction * 1000) >> 32))
+}
+
+async fn sync(stack: Stack<'static>) -> Option<u64> {
+    let server = crate::client::resolve(stack, SERVER).await?;
+
+    let buffers: UdpBuffers<1, 128, 128, 1> = UdpBuffers::new();
+    let udp = Udp::new(stack, &buffers);
+    let mut socket = udp
+        .bind(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)))
+        .await
+        .inspect_err(|e| log::warn!("SNTP: bind failed: {:?}", e))
+        .ok()?;
+
+    let remote = SocketAddr::new(IpAddr::V4(server), NTP_PORT);
+    let sent_at = Instant::now();
+    edge_nal::UdpSend::send(&mut socket, remote, &request())
+        .await
+        .inspect_err(|e| log::warn!("SNTP: send failed: {:?}", e))
+        .ok()?;
+    netstat::udp_sent();
+
+    let mut buf = [0u8; 128];
+    let (len, _) = with_timeout(
+        Duration::from_secs(5),
+        edge_nal::UdpReceive::receive(&mut socket, &mut buf),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    netstat::udp_received();
+
+    // assume the response took half of the round trip
+    let half_rtt = (Instant::now() - sent_at).as_millis() / 2;
+    parse_response(&buf[..len]).map(|ms| ms + half_rtt)
+}
+
+/// Keeps [`clock`] in sync with an NTP server.
+#[embassy_executor::task]
+pub async fn sntp_task(stack: Stack<'static>) {
+    loop {
+        stack.wait_config_up().await;
+
+        match sync(stack).await {
+            Some(unix_ms) => {
+                clock::set_unix_time(unix_ms);
+                if let Some(now) = clock::now_local() {
+                    log::info!("SNTP: synced with {}, local time {}", SERVER, now);
+                }
+                Timer::after(RESYNC_INTERVAL).await;
+            }
+            None => {
+                log::warn!("SNTP: sync failed, retrying...");
+                Timer::after(RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
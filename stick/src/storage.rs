@@ -0,0 +1,98 @@
+//! [`Settings`] in the `storage` data partition (see `partitions.csv`).
+//!
+//! Also the one owner of the flash, OTA goes through [`with_flash`] too. Every access is a
+//! single synchronous call with the driver to itself, so a save never lands in the middle of
+//! an OTA write.
+
+use core::cell::RefCell;
+
+use app::settings::{MAX_RECORD_SIZE, Settings};
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+use esp_bootloader_esp_idf::partitions::{
+    self, DataPartitionSubType, PARTITION_TABLE_MAX_LEN, PartitionType,
+};
+use esp_hal::peripherals::FLASH;
+use esp_storage::FlashStorage;
+
+const SECTOR_SIZE: u32 = 4096;
+const WRITE_ALIGN: usize = 4;
+
+#[derive(Debug)]
+pub enum Error {
+    Partitions(partitions::Error),
+    /// The partition table has no `storage` partition, flashed without `partitions.csv`?
+    NoPartition,
+    Flash(NorFlashErrorKind),
+}
+
+impl From<partitions::Error> for Error {
+    fn from(e: partitions::Error) -> Self {
+        Error::Partitions(e)
+    }
+}
+
+static FLASH_STORAGE: Mutex<CriticalSectionRawMutex, RefCell<Option<FlashStorage<'static>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Takes the flash for good, before anything else uses it.
+pub fn init(flash: FLASH<'static>) {
+    FLASH_STORAGE.lock(|storage| *storage.borrow_mut() = Some(FlashStorage::new(flash)));
+}
+
+/// Runs `f` with the flash to itself, `f` must not call back in here.
+///
+/// The driver is taken out for `f` and put back after, the critical section only covers
+/// that and not a whole sector erase. `f` doesn't await, so no other task gets in between.
+pub fn with_flash<T>(f: impl FnOnce(&mut FlashStorage<'static>) -> T) -> T {
+    let mut flash = FLASH_STORAGE
+        .lock(|storage| storage.borrow_mut().take())
+        .expect("flash in use, or storage::init wasn't called");
+    let result = f(&mut flash);
+    FLASH_STORAGE.lock(|storage| *storage.borrow_mut() = Some(flash));
+    result
+}
+
+fn with_partition<T>(
+    f: impl FnOnce(&mut partitions::FlashRegion<'_, FlashStorage<'static>>) -> Result<T, Error>,
+) -> Result<T, Error> {
+    with_flash(|flash| {
+        let mut buffer = [0u8; PARTITION_TABLE_MAX_LEN];
+        let table = partitions::read_partition_table(flash, &mut buffer)?;
+        let entry = table
+            .find_partition(PartitionType::Data(DataPartitionSubType::Undefined))?
+            .ok_or(Error::NoPartition)?;
+        let mut region = entry.as_embedded_storage(flash);
+        f(&mut region)
+    })
+}
+
+/// Saved settings, defaults if there are none (or they don't check out).
+pub fn load() -> Settings {
+    let result = with_partition(|region| {
+        let mut record = [0u8; MAX_RECORD_SIZE];
+        region
+            .read(0, &mut record)
+            .map_err(|e| Error::Flash(e.kind()))?;
+        Ok(Settings::from_record(&record))
+    });
+    match result {
+        Ok(Some(settings)) => settings,
+        Ok(None) => Settings::default(),
+        Err(e) => {
+            log::warn!("storage: can't load settings: {:?}", e);
+            Settings::default()
+        }
+    }
+}
+
+pub fn save(settings: &Settings) -> Result<(), Error> {
+    let mut record = settings.to_record();
+    record.resize(record.len().next_multiple_of(WRITE_ALIGN), 0xFF);
+    with_partition(|region| {
+        region
+            .erase(0, SECTOR_SIZE)
+            .and_then(|_| region.write(0, &record))
+            .map_err(|e| Error::Flash(e.kind()))
+    })
+}
@@ -0,0 +1,59 @@
+use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use app::{logger, syslog};
+use edge_nal::UdpBind;
+use edge_nal_embassy::{Udp, UdpBuffers};
+use embassy_net::Stack;
+use embassy_time::{Duration, Timer};
+
+use crate::netstat;
+
+/// Records kept while the network is not up yet.
+pub const BUFFER_CAPACITY: usize = 64;
+
+/// Collector address, override with `SYSLOG_COLLECTOR=ip:port` at build time.
+pub const COLLECTOR: &str = match option_env!("SYSLOG_COLLECTOR") {
+    Some(addr) => addr,
+    None => "192.168.2.2:514",
+};
+
+/// Forwards buffered log records to the collector once the stack has an address.
+///
+/// Call [`logger::enable_remote_sink`] before anything interesting is logged.
+#[embassy_executor::task]
+pub async fn syslog_task(stack: Stack<'static>, collector: SocketAddr) {
+    stack.wait_config_up().await;
+
+    let buffers: UdpBuffers<1, 512, 512, 1> = UdpBuffers::new();
+    let udp = Udp::new(stack, &buffers);
+
+    let mut socket = match udp
+        .bind(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)))
+        .await
+    {
+        Ok(s) => s,
+        Err(e) => {
+            log::error!("syslog bind failed: {:?}", e);
+            return;
+        }
+    };
+
+    let hostname = crate::hostname();
+    log::info!("syslog: forwarding to {} as {}", collector, hostname);
+
+    loop {
+        for record in logger::take_remote_records().await {
+            let line = syslog::format(&record, &hostname, "stick");
+            let len = line.len().min(512);
+            // Logging the error here would feed the sink again, just drop the record.
+            if edge_nal::UdpSend::send(&mut socket, collector, &line.as_bytes()[..len])
+                .await
+                .is_err()
+            {
+                Timer::after(Duration::from_millis(100)).await;
+            } else {
+                netstat::udp_sent();
+            }
+        }
+    }
+}
@@ -37,6 +37,11 @@ pub async fn vehicle_task(mut receiver: Receiver, mut servos: Hat8Servos) {
                     .await
                     .ok();
             }
+            // before the stick cuts its own power, the servos could be mid-move
+            Event::PowerOff => {
+                servos.enable_power(false).await.ok();
+                power_on = false;
+            }
             Event::ButtonUp(Button::A) => {
                 if power_on {
                     servos.enable_power(false).await.ok();